@@ -0,0 +1,326 @@
+//! A bounded channel over a lock-free ring buffer, with no `Mutex` or
+//! `Condvar` on the send or receive path unless one side has to wait.
+//!
+//! Slots carry a stamp recording which lap of the ring they are on, so
+//! senders and the receiver claim them with a single compare-exchange on
+//! `tail` or `head`. A blocked sender or receiver registers its `Thread` and
+//! is unparked directly by the other side once there is room or a value.
+
+use crate::lockfree::{self, Queue};
+use crate::parker::Parker;
+use crate::{RecvTimeoutError, SendError, TryRecvError, deadline_after};
+use std::cell::UnsafeCell;
+use std::mem::MaybeUninit;
+use std::sync::atomic::{AtomicUsize, Ordering, fence};
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub struct Sender<T> {
+    inner: lockfree::Sender<Inner<T>>,
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> Sender<T> {
+    /// Sends a value to the receiver, handing it back if the receiver is
+    /// gone. Parks while the buffer is full.
+    pub fn send(&self, mut value: T) -> Result<(), SendError<T>> {
+        let chan = &self.inner.chan;
+        loop {
+            if chan.is_receiver_dropped() {
+                return Err(SendError(value));
+            }
+            match chan.queue.push(value) {
+                Ok(()) => {
+                    chan.not_empty.unpark_all();
+                    return Ok(());
+                }
+                Err(rejected) => value = rejected,
+            }
+            chan.queue
+                .not_full
+                .park_until(None, || chan.queue.is_full() && !chan.is_receiver_dropped());
+        }
+    }
+}
+
+pub struct Receiver<T> {
+    inner: lockfree::Receiver<Inner<T>>,
+}
+
+impl<T> Receiver<T> {
+    pub fn recv(&mut self) -> Option<T> {
+        self.inner.recv_until(None).ok()
+    }
+
+    /// Blocks for at most `timeout` waiting for a value.
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        self.inner.recv_until(deadline_after(timeout))
+    }
+
+    /// Blocks until `deadline` at the latest waiting for a value.
+    pub fn recv_deadline(&mut self, deadline: Instant) -> Result<T, RecvTimeoutError> {
+        self.inner.recv_until(Some(deadline))
+    }
+
+    /// Receives a value if one is ready, without blocking.
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        self.inner.try_recv()
+    }
+}
+
+impl<T> Iterator for Receiver<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.recv()
+    }
+}
+
+struct Slot<T> {
+    /// Packed like `tail`: the index a sender may claim the slot at while it
+    /// is free, or that index plus one once the value is written.
+    stamp: AtomicUsize,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+struct Inner<T> {
+    /// Next index to read, as lap and slot index packed together.
+    head: AtomicUsize,
+    /// Next index to write, packed like `head`.
+    tail: AtomicUsize,
+    buffer: Box<[Slot<T>]>,
+    /// Smallest power of two above the capacity; the low bits of `head` and
+    /// `tail` are the slot index and the rest count laps.
+    one_lap: usize,
+    /// Parks senders while the buffer is full.
+    not_full: Parker,
+}
+
+// SAFETY: a slot is only written by the sender that claimed it and only read
+// by the receiver that claimed it, with stamps ordering the two.
+unsafe impl<T: Send> Send for Inner<T> {}
+unsafe impl<T: Send> Sync for Inner<T> {}
+
+impl<T> Inner<T> {
+    /// Pushes a value, handing it back if the buffer is full.
+    fn push(&self, value: T) -> Result<(), T> {
+        let mut tail = self.tail.load(Ordering::Relaxed);
+        loop {
+            let index = tail & (self.one_lap - 1);
+            let lap = tail & !(self.one_lap - 1);
+            let new_tail = if index + 1 < self.buffer.len() {
+                tail + 1
+            } else {
+                lap.wrapping_add(self.one_lap)
+            };
+            let slot = &self.buffer[index];
+            let stamp = slot.stamp.load(Ordering::Acquire);
+            if tail == stamp {
+                // The slot is free on this lap; try to claim it.
+                match self.tail.compare_exchange_weak(
+                    tail,
+                    new_tail,
+                    Ordering::SeqCst,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => {
+                        // SAFETY: winning the exchange made the slot ours.
+                        unsafe { slot.value.get().write(MaybeUninit::new(value)) };
+                        slot.stamp.store(tail + 1, Ordering::Release);
+                        return Ok(());
+                    }
+                    Err(current) => tail = current,
+                }
+            } else if stamp.wrapping_add(self.one_lap) == tail + 1 {
+                // The slot still holds a value from the previous lap.
+                fence(Ordering::SeqCst);
+                let head = self.head.load(Ordering::Relaxed);
+                if head.wrapping_add(self.one_lap) == tail {
+                    return Err(value);
+                }
+                tail = self.tail.load(Ordering::Relaxed);
+            } else {
+                // Another sender claimed the slot and has not moved `tail`
+                // on yet.
+                thread::yield_now();
+                tail = self.tail.load(Ordering::Relaxed);
+            }
+        }
+    }
+
+    fn pop(&self) -> Option<T> {
+        let mut head = self.head.load(Ordering::Relaxed);
+        loop {
+            let index = head & (self.one_lap - 1);
+            let lap = head & !(self.one_lap - 1);
+            let slot = &self.buffer[index];
+            let stamp = slot.stamp.load(Ordering::Acquire);
+            if head + 1 == stamp {
+                // The slot was written on this lap; try to claim it.
+                let new_head = if index + 1 < self.buffer.len() {
+                    head + 1
+                } else {
+                    lap.wrapping_add(self.one_lap)
+                };
+                match self.head.compare_exchange_weak(
+                    head,
+                    new_head,
+                    Ordering::SeqCst,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => {
+                        // SAFETY: winning the exchange made the slot ours,
+                        // and its stamp says it was written.
+                        let value = unsafe { slot.value.get().read().assume_init() };
+                        slot.stamp
+                            .store(head.wrapping_add(self.one_lap), Ordering::Release);
+                        return Some(value);
+                    }
+                    Err(current) => head = current,
+                }
+            } else if stamp == head {
+                // The slot has not been written on this lap.
+                fence(Ordering::SeqCst);
+                let tail = self.tail.load(Ordering::Relaxed);
+                if tail == head {
+                    return None;
+                }
+                head = self.head.load(Ordering::Relaxed);
+            } else {
+                // A sender claimed the slot but is still writing it.
+                thread::yield_now();
+                head = self.head.load(Ordering::Relaxed);
+            }
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        let head = self.head.load(Ordering::SeqCst);
+        let tail = self.tail.load(Ordering::SeqCst);
+        head == tail
+    }
+
+    fn is_full(&self) -> bool {
+        let tail = self.tail.load(Ordering::SeqCst);
+        let head = self.head.load(Ordering::SeqCst);
+        head.wrapping_add(self.one_lap) == tail
+    }
+}
+
+impl<T> Queue for Inner<T> {
+    type Item = T;
+
+    unsafe fn pop(&self) -> Option<T> {
+        Inner::pop(self)
+    }
+
+    unsafe fn is_empty(&self) -> bool {
+        Inner::is_empty(self)
+    }
+
+    fn popped(&self) {
+        self.not_full.unpark_all();
+    }
+
+    fn receiver_gone(&self) {
+        self.not_full.unpark_all();
+    }
+}
+
+impl<T> Drop for Inner<T> {
+    fn drop(&mut self) {
+        while self.pop().is_some() {}
+    }
+}
+
+/// Creates a bounded lock-free channel holding at most `capacity` values.
+///
+/// # Panics
+///
+/// Panics if `capacity` is zero.
+pub fn sync_channel<T>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    assert!(capacity > 0, "sync_channel capacity must be non-zero");
+    let buffer = (0..capacity)
+        .map(|index| Slot {
+            stamp: AtomicUsize::new(index),
+            value: UnsafeCell::new(MaybeUninit::uninit()),
+        })
+        .collect();
+    let (tx, rx) = lockfree::new(Inner {
+        head: AtomicUsize::new(0),
+        tail: AtomicUsize::new(0),
+        buffer,
+        one_lap: (capacity + 1).next_power_of_two(),
+        not_full: Parker::new(),
+    });
+    (Sender { inner: tx }, Receiver { inner: rx })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sync_channel;
+    use crate::{SendError, TryRecvError};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn blocks_when_full() {
+        let (tx, mut rx) = sync_channel(2);
+        let sent = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&sent);
+        let handle = thread::spawn(move || {
+            for i in 0..4 {
+                tx.send(i).unwrap();
+                counter.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+        while sent.load(Ordering::SeqCst) < 2 {
+            thread::yield_now();
+        }
+        // The third send must stay blocked until a value is received.
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(sent.load(Ordering::SeqCst), 2);
+        assert_eq!(rx.recv(), Some(0));
+        assert_eq!(rx.recv(), Some(1));
+        assert_eq!(rx.recv(), Some(2));
+        assert_eq!(rx.recv(), Some(3));
+        assert_eq!(rx.recv(), None);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn many_senders() {
+        let (tx, rx) = sync_channel(4);
+        for t in 0..8 {
+            let tx = tx.clone();
+            thread::spawn(move || {
+                for i in 0..1000 {
+                    tx.send(t * 1000 + i).unwrap();
+                }
+            });
+        }
+        drop(tx);
+        let mut values: Vec<usize> = rx.collect();
+        values.sort();
+        assert_eq!(values, (0..8000).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn drop_receiver_wakes_blocked_sender() {
+        let (tx, mut rx) = sync_channel(1);
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+        tx.send(1).unwrap();
+        let handle = thread::spawn(move || tx.send(2));
+        thread::sleep(Duration::from_millis(50));
+        drop(rx);
+        assert_eq!(handle.join().unwrap(), Err(SendError(2)));
+    }
+}
@@ -0,0 +1,298 @@
+//! An unbounded channel over a lock-free linked list of blocks.
+//!
+//! Each block holds a fixed array of slots. Senders claim a slot by bumping
+//! a single `tail` index and only allocate when they claim the last slot of
+//! a block, so allocation is amortised over a block's worth of messages. As in
+//! [`list`](crate::list), blocks are only freed by the single receiver,
+//! which parks only when nothing is ready.
+
+use crate::lockfree::{self, Queue};
+use crate::{RecvTimeoutError, SendError, TryRecvError, deadline_after};
+use std::cell::UnsafeCell;
+use std::mem::MaybeUninit;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Slots per block.
+const BLOCK_CAP: usize = 31;
+/// Indices per block: one per slot, plus one that marks the block as full
+/// while its successor is being installed.
+const LAP: usize = BLOCK_CAP + 1;
+
+pub struct Sender<T> {
+    inner: lockfree::Sender<Inner<T>>,
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> Sender<T> {
+    /// Sends a value to the receiver, handing it back if the receiver is
+    /// gone. Never blocks.
+    pub fn send(&self, value: T) -> Result<(), SendError<T>> {
+        let chan = &self.inner.chan;
+        if chan.is_receiver_dropped() {
+            return Err(SendError(value));
+        }
+        chan.queue.push(value);
+        chan.not_empty.unpark_all();
+        Ok(())
+    }
+}
+
+pub struct Receiver<T> {
+    inner: lockfree::Receiver<Inner<T>>,
+}
+
+impl<T> Receiver<T> {
+    pub fn recv(&mut self) -> Option<T> {
+        self.inner.recv_until(None).ok()
+    }
+
+    /// Blocks for at most `timeout` waiting for a value.
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        self.inner.recv_until(deadline_after(timeout))
+    }
+
+    /// Blocks until `deadline` at the latest waiting for a value.
+    pub fn recv_deadline(&mut self, deadline: Instant) -> Result<T, RecvTimeoutError> {
+        self.inner.recv_until(Some(deadline))
+    }
+
+    /// Receives a value if one is ready, without blocking.
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        self.inner.try_recv()
+    }
+}
+
+impl<T> Iterator for Receiver<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.recv()
+    }
+}
+
+struct Slot<T> {
+    value: UnsafeCell<MaybeUninit<T>>,
+    ready: AtomicBool,
+}
+
+struct Block<T> {
+    next: AtomicPtr<Block<T>>,
+    slots: [Slot<T>; BLOCK_CAP],
+}
+
+impl<T> Block<T> {
+    fn new() -> Box<Self> {
+        Box::new(Self {
+            next: AtomicPtr::new(ptr::null_mut()),
+            slots: std::array::from_fn(|_| Slot {
+                value: UnsafeCell::new(MaybeUninit::uninit()),
+                ready: AtomicBool::new(false),
+            }),
+        })
+    }
+}
+
+/// Where the receiver reads next. Only the receiver touches it.
+struct Head<T> {
+    index: usize,
+    block: *mut Block<T>,
+}
+
+struct Inner<T> {
+    head: UnsafeCell<Head<T>>,
+    /// Index of the next slot to claim.
+    tail: AtomicUsize,
+    /// Block holding the slot at `tail`.
+    tail_block: AtomicPtr<Block<T>>,
+}
+
+// SAFETY: each slot is written by the one sender that claimed it and read by
+// the single receiver once marked ready; `head` is confined to the receiver.
+unsafe impl<T: Send> Send for Inner<T> {}
+unsafe impl<T: Send> Sync for Inner<T> {}
+
+impl<T> Inner<T> {
+    fn push(&self, value: T) {
+        let mut next_block = None;
+        loop {
+            // Load the index before the block: the block is swapped before
+            // the index moves on, so a stale block fails the exchange below.
+            let tail = self.tail.load(Ordering::Acquire);
+            let offset = tail % LAP;
+            if offset == BLOCK_CAP {
+                // Another sender is installing the next block.
+                thread::yield_now();
+                continue;
+            }
+            let block = self.tail_block.load(Ordering::Acquire);
+            if offset + 1 == BLOCK_CAP && next_block.is_none() {
+                next_block = Some(Block::new());
+            }
+            if self
+                .tail
+                .compare_exchange_weak(tail, tail + 1, Ordering::SeqCst, Ordering::Relaxed)
+                .is_err()
+            {
+                continue;
+            }
+            // SAFETY: the slot at `offset` is ours, and the receiver cannot
+            // free `block` before reading it.
+            unsafe {
+                if offset + 1 == BLOCK_CAP {
+                    let next = Box::into_raw(next_block.take().unwrap());
+                    self.tail_block.store(next, Ordering::Release);
+                    self.tail.fetch_add(1, Ordering::Release);
+                    (*block).next.store(next, Ordering::Release);
+                }
+                let slot = &(*block).slots[offset];
+                slot.value.get().write(MaybeUninit::new(value));
+                slot.ready.store(true, Ordering::Release);
+            }
+            return;
+        }
+    }
+}
+
+impl<T> Queue for Inner<T> {
+    type Item = T;
+
+    /// Takes the next value. A slot that has been claimed but not yet
+    /// written reads as empty; its sender unparks the receiver once done.
+    unsafe fn pop(&self) -> Option<T> {
+        unsafe {
+            if self.is_empty() {
+                return None;
+            }
+            let head = &mut *self.head.get();
+            let offset = head.index % LAP;
+            let slot = &(*head.block).slots[offset];
+            let value = slot.value.get().read().assume_init();
+            if offset + 1 == BLOCK_CAP {
+                // The sender of the last slot linked the successor before
+                // marking the slot ready.
+                let next = (*head.block).next.load(Ordering::Acquire);
+                drop(Box::from_raw(head.block));
+                head.block = next;
+                head.index += 2;
+            } else {
+                head.index += 1;
+            }
+            Some(value)
+        }
+    }
+
+    unsafe fn is_empty(&self) -> bool {
+        unsafe {
+            let head = &*self.head.get();
+            // `head` never rests on a block's marker index, so it is past
+            // `tail` while the next block has no claimed slots yet.
+            if head.index >= self.tail.load(Ordering::Acquire) {
+                return true;
+            }
+            let slot = &(*head.block).slots[head.index % LAP];
+            !slot.ready.load(Ordering::Acquire)
+        }
+    }
+}
+
+impl<T> Drop for Inner<T> {
+    fn drop(&mut self) {
+        // SAFETY: with every handle gone all claimed slots are written, and
+        // only the block under `head` is left once they are drained.
+        unsafe {
+            while self.pop().is_some() {}
+            drop(Box::from_raw(self.head.get_mut().block));
+        }
+    }
+}
+
+/// Creates an unbounded lock-free channel that stores values in blocks of
+/// 31 slots.
+pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+    let block = Box::into_raw(Block::new());
+    let (tx, rx) = lockfree::new(Inner {
+        head: UnsafeCell::new(Head { index: 0, block }),
+        tail: AtomicUsize::new(0),
+        tail_block: AtomicPtr::new(block),
+    });
+    (Sender { inner: tx }, Receiver { inner: rx })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BLOCK_CAP, channel};
+    use crate::{RecvTimeoutError, SendError, TryRecvError};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn crosses_blocks_in_order() {
+        let (tx, mut rx) = channel();
+        let count = BLOCK_CAP * 3 + 5;
+        for i in 0..count {
+            tx.send(i).unwrap();
+        }
+        for i in 0..count {
+            assert_eq!(rx.try_recv(), Ok(i));
+        }
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+        drop(tx);
+        assert_eq!(rx.recv(), None);
+    }
+
+    #[test]
+    fn many_senders() {
+        let (tx, rx) = channel();
+        for t in 0..8 {
+            let tx = tx.clone();
+            thread::spawn(move || {
+                for i in 0..10_000 {
+                    tx.send(t * 10_000 + i).unwrap();
+                }
+            });
+        }
+        drop(tx);
+        let mut values: Vec<usize> = rx.collect();
+        values.sort();
+        assert_eq!(values, (0..80_000).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn unreceived_values_are_dropped() {
+        let value = Arc::new(());
+        let (tx, rx) = channel();
+        for _ in 0..BLOCK_CAP + 1 {
+            tx.send(Arc::clone(&value)).unwrap();
+        }
+        drop(rx);
+        assert_eq!(Arc::strong_count(&value), 1);
+        assert_eq!(
+            tx.send(Arc::clone(&value)).map_err(|SendError(_)| ()),
+            Err(())
+        );
+    }
+
+    #[test]
+    fn recv_timeout() {
+        let (tx, mut rx) = channel::<i32>();
+        assert_eq!(
+            rx.recv_timeout(Duration::from_millis(10)),
+            Err(RecvTimeoutError::Timeout)
+        );
+        drop(tx);
+        assert_eq!(
+            rx.recv_timeout(Duration::from_millis(10)),
+            Err(RecvTimeoutError::Disconnected)
+        );
+    }
+}
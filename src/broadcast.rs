@@ -0,0 +1,306 @@
+//! A channel where every receiver sees every value.
+//!
+//! Values are kept in a ring of fixed capacity and cloned out to each
+//! receiver. A receiver that falls more than a ring behind misses the
+//! overwritten values and is told how many with [`RecvError::Lagged`].
+
+use crate::{SendError, lock, wait_until};
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::sync::{Arc, Condvar, Mutex};
+
+pub struct Sender<T> {
+    inner: Arc<Inner<T>>,
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        let mut shared = lock(&self.inner.shared);
+        shared.senders += 1;
+        drop(shared);
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut shared = lock(&self.inner.shared);
+        shared.senders -= 1;
+        let is_last = shared.senders == 0;
+        drop(shared);
+        if is_last {
+            self.inner.available.notify_all()
+        }
+    }
+}
+
+impl<T> Sender<T> {
+    /// Publishes a value to every current receiver, overwriting the oldest
+    /// one if the ring is full. Fails if there are no receivers.
+    pub fn send(&self, value: T) -> Result<(), SendError<T>> {
+        let mut shared = lock(&self.inner.shared);
+        if shared.receivers == 0 {
+            return Err(SendError(value));
+        }
+        let mut evicted = None;
+        if shared.ring.len() == self.inner.capacity {
+            shared.head += 1;
+            evicted = shared.ring.pop_front();
+        }
+        shared.ring.push_back(value);
+        drop(shared);
+        self.inner.available.notify_all();
+        // Dropped unlocked: a panicking `Drop` must not poison the ring.
+        drop(evicted);
+        Ok(())
+    }
+
+    /// Creates a receiver that sees every value sent from now on.
+    pub fn subscribe(&self) -> Receiver<T> {
+        let mut shared = lock(&self.inner.shared);
+        shared.receivers += 1;
+        let next = shared.head + shared.ring.len() as u64;
+        drop(shared);
+        Receiver {
+            inner: Arc::clone(&self.inner),
+            next,
+        }
+    }
+}
+
+pub struct Receiver<T> {
+    inner: Arc<Inner<T>>,
+    /// Sequence number of the next value to receive.
+    next: u64,
+}
+
+/// The clone continues from the same position.
+impl<T> Clone for Receiver<T> {
+    fn clone(&self) -> Self {
+        let mut shared = lock(&self.inner.shared);
+        shared.receivers += 1;
+        drop(shared);
+        Self {
+            inner: Arc::clone(&self.inner),
+            next: self.next,
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let mut shared = lock(&self.inner.shared);
+        shared.receivers -= 1;
+    }
+}
+
+impl<T: Clone> Receiver<T> {
+    /// Blocks until the next value is published.
+    ///
+    /// If values were overwritten before this receiver got to them, returns
+    /// [`RecvError::Lagged`] once and then continues from the oldest value
+    /// still in the ring.
+    pub fn recv(&mut self) -> Result<T, RecvError> {
+        let mut shared = lock(&self.inner.shared);
+        loop {
+            match shared.take(&mut self.next) {
+                Err(TryRecvError::Empty) => {
+                    shared = wait_until(&self.inner.available, shared, None).0;
+                }
+                Err(TryRecvError::Lagged(skipped)) => return Err(RecvError::Lagged(skipped)),
+                Err(TryRecvError::Closed) => return Err(RecvError::Closed),
+                Ok(value) => return Ok(value),
+            }
+        }
+    }
+
+    /// Receives the next value if it has been published, without blocking.
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        let shared = lock(&self.inner.shared);
+        shared.take(&mut self.next)
+    }
+}
+
+struct Inner<T> {
+    shared: Mutex<Shared<T>>,
+    available: Condvar,
+    capacity: usize,
+}
+
+struct Shared<T> {
+    ring: VecDeque<T>,
+    /// Sequence number of `ring[0]`.
+    head: u64,
+    senders: usize,
+    receivers: usize,
+}
+
+impl<T: Clone> Shared<T> {
+    /// Clones out the value at `next` and advances it.
+    fn take(&self, next: &mut u64) -> Result<T, TryRecvError> {
+        if *next < self.head {
+            let skipped = self.head - *next;
+            *next = self.head;
+            return Err(TryRecvError::Lagged(skipped));
+        }
+        match self.ring.get((*next - self.head) as usize) {
+            Some(value) => {
+                *next += 1;
+                Ok(value.clone())
+            }
+            None if self.senders == 0 => Err(TryRecvError::Closed),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+}
+
+/// Returned by [`Receiver::recv`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecvError {
+    /// The receiver fell behind and this many values were overwritten.
+    Lagged(u64),
+    /// Every sender is gone and all values were received.
+    Closed,
+}
+
+impl fmt::Display for RecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecvError::Lagged(skipped) => write!(f, "receiver lagged by {skipped} values"),
+            RecvError::Closed => f.write_str("receiving on a closed broadcast channel"),
+        }
+    }
+}
+
+impl Error for RecvError {}
+
+/// Returned by [`Receiver::try_recv`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TryRecvError {
+    /// No new value has been published yet.
+    Empty,
+    /// The receiver fell behind and this many values were overwritten.
+    Lagged(u64),
+    /// Every sender is gone and all values were received.
+    Closed,
+}
+
+impl fmt::Display for TryRecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TryRecvError::Empty => f.write_str("receiving on an empty broadcast channel"),
+            TryRecvError::Lagged(skipped) => write!(f, "receiver lagged by {skipped} values"),
+            TryRecvError::Closed => f.write_str("receiving on a closed broadcast channel"),
+        }
+    }
+}
+
+impl Error for TryRecvError {}
+
+/// Creates a broadcast channel keeping the last `capacity` values for slow
+/// receivers.
+///
+/// # Panics
+///
+/// Panics if `capacity` is zero.
+pub fn channel<T>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    assert!(capacity > 0, "broadcast capacity must be non-zero");
+    let inner = Arc::new(Inner {
+        shared: Mutex::new(Shared {
+            ring: VecDeque::with_capacity(capacity),
+            head: 0,
+            senders: 1,
+            receivers: 1,
+        }),
+        available: Condvar::new(),
+        capacity,
+    });
+    (
+        Sender {
+            inner: inner.clone(),
+        },
+        Receiver { inner, next: 0 },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RecvError, TryRecvError, channel};
+    use crate::SendError;
+    use std::thread;
+
+    #[test]
+    fn every_receiver_sees_every_value() {
+        let (tx, rx) = channel(16);
+        let subscribers: Vec<_> = (0..3)
+            .map(|_| {
+                let mut rx = tx.subscribe();
+                thread::spawn(move || {
+                    let mut values = Vec::new();
+                    while let Ok(value) = rx.recv() {
+                        values.push(value);
+                    }
+                    values
+                })
+            })
+            .collect();
+        drop(rx);
+        for i in 0..10 {
+            tx.send(i).unwrap();
+        }
+        drop(tx);
+        for subscriber in subscribers {
+            assert_eq!(subscriber.join().unwrap(), (0..10).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn slow_receiver_lags() {
+        let (tx, mut rx) = channel(2);
+        for i in 0..5 {
+            tx.send(i).unwrap();
+        }
+        assert_eq!(rx.recv(), Err(RecvError::Lagged(3)));
+        assert_eq!(rx.recv(), Ok(3));
+        assert_eq!(rx.try_recv(), Ok(4));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+        drop(tx);
+        assert_eq!(rx.recv(), Err(RecvError::Closed));
+    }
+
+    #[test]
+    fn subscribe_sees_only_new_values() {
+        let (tx, rx) = channel(4);
+        tx.send(1).unwrap();
+        let mut late = tx.subscribe();
+        tx.send(2).unwrap();
+        assert_eq!(late.try_recv(), Ok(2));
+        drop(rx);
+        drop(late);
+        assert_eq!(tx.send(3), Err(SendError(3)));
+    }
+
+    #[derive(Clone)]
+    struct PanicOnDrop(bool);
+
+    impl Drop for PanicOnDrop {
+        fn drop(&mut self) {
+            if self.0 && !thread::panicking() {
+                panic!("dropped");
+            }
+        }
+    }
+
+    #[test]
+    fn panicking_drop_of_evicted_value_is_reported_as_lag() {
+        let (tx, mut rx) = channel(1);
+        tx.send(PanicOnDrop(true)).unwrap();
+        let result = thread::scope(|scope| scope.spawn(|| tx.send(PanicOnDrop(false))).join());
+        assert!(result.is_err());
+        assert!(matches!(rx.recv(), Err(RecvError::Lagged(1))));
+        assert!(matches!(rx.recv(), Ok(PanicOnDrop(false))));
+    }
+}
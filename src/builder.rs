@@ -0,0 +1,186 @@
+use crate::{Inner, OverflowPolicy, QueueStorage, Receiver, Sender, Shared, Wakers};
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use std::sync::atomic::AtomicUsize;
+use std::sync::{Arc, Condvar, Mutex};
+
+/// Configures a channel before creating it, as an alternative to the
+/// constructor functions:
+///
+/// ```
+/// use falgu_rs::{ChannelBuilder, OverflowPolicy};
+/// use std::collections::LinkedList;
+///
+/// let (tx, mut rx) = ChannelBuilder::new()
+///     .name("samples")
+///     .capacity(1024)
+///     .overflow(OverflowPolicy::DropOldest)
+///     .backend::<LinkedList<u64>>()
+///     .build();
+/// tx.send(1).unwrap();
+/// assert_eq!(rx.name(), Some("samples"));
+/// assert_eq!(rx.recv(), Some(1));
+/// ```
+pub struct ChannelBuilder<T, S: QueueStorage<Item = T> = VecDeque<T>> {
+    capacity: Option<usize>,
+    overflow: OverflowPolicy,
+    preallocate: usize,
+    name: Option<String>,
+    marker: PhantomData<S>,
+}
+
+impl<T> ChannelBuilder<T> {
+    /// Starts from an unbounded channel over a `VecDeque`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<T, S: QueueStorage<Item = T>> Default for ChannelBuilder<T, S> {
+    fn default() -> Self {
+        Self {
+            capacity: None,
+            overflow: OverflowPolicy::Block,
+            preallocate: 0,
+            name: None,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T, S: QueueStorage<Item = T>> ChannelBuilder<T, S> {
+    /// Bounds the channel to `capacity` values. Zero makes a rendezvous
+    /// channel.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = Some(capacity);
+        self
+    }
+
+    /// Sets what a send to a full bounded channel does. Unbounded channels
+    /// are never full.
+    pub fn overflow(mut self, policy: OverflowPolicy) -> Self {
+        self.overflow = policy;
+        self
+    }
+
+    /// Allocates room for `len` values up front, so early sends do not
+    /// reallocate the queue. Only storage with a
+    /// [`QueueStorage::with_capacity`] of its own makes use of it.
+    pub fn preallocate(mut self, len: usize) -> Self {
+        self.preallocate = len;
+        self
+    }
+
+    /// Names the channel, as shown by `Debug` and by `name()` on either
+    /// half.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Switches the queue storage, e.g. to `backend::<LinkedList<T>>()`.
+    pub fn backend<B: QueueStorage<Item = T>>(self) -> ChannelBuilder<T, B> {
+        ChannelBuilder {
+            capacity: self.capacity,
+            overflow: self.overflow,
+            preallocate: self.preallocate,
+            name: self.name,
+            marker: PhantomData,
+        }
+    }
+
+    /// Creates the channel.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a rendezvous channel is given an overflow policy other
+    /// than [`OverflowPolicy::Block`].
+    pub fn build(self) -> (Sender<T, S>, Receiver<T, S>) {
+        // Without a bound the receiver's batch and the queue swap
+        // allocations, so both get one; a bounded receiver never batches.
+        let buffer = match self.capacity {
+            None => S::with_capacity(self.preallocate),
+            Some(_) => S::default(),
+        };
+        let inner = self.build_inner();
+        (
+            Sender::new(inner.clone()),
+            Receiver {
+                inner,
+                buffer,
+                waker_id: None,
+            },
+        )
+    }
+
+    pub(crate) fn build_inner(self) -> Arc<Inner<T, S>> {
+        assert!(
+            self.capacity != Some(0) || self.overflow == OverflowPolicy::Block,
+            "a rendezvous channel can only block"
+        );
+        Arc::new(Inner {
+            shared: Mutex::new(Shared {
+                queue: S::with_capacity(self.preallocate),
+                senders: 1,
+                receivers: 1,
+                received: 0,
+                reserved: 0,
+                dropped: 0,
+                closed: false,
+                recv_wakers: Wakers::default(),
+                send_wakers: Wakers::default(),
+                marker: PhantomData,
+            }),
+            available: Condvar::new(),
+            space: Condvar::new(),
+            capacity: self.capacity,
+            overflow: self.overflow,
+            name: self.name,
+            buffered: AtomicUsize::new(0),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ChannelBuilder;
+    use crate::{OverflowPolicy, TrySendError};
+    use std::thread;
+
+    #[test]
+    fn builds_configured_channel() {
+        let (tx, mut rx) = ChannelBuilder::new()
+            .name("jobs")
+            .capacity(2)
+            .overflow(OverflowPolicy::Reject)
+            .preallocate(2)
+            .build();
+        tx.send_all([1, 2]).unwrap();
+        assert_eq!(tx.try_send(3), Err(TrySendError::Full(3)));
+        assert_eq!(tx.name(), Some("jobs"));
+        assert_eq!(rx.capacity(), Some(2));
+        assert_eq!(
+            format!("{rx:?}"),
+            r#"Receiver { name: Some("jobs"), capacity: Some(2), overflow: Reject, len: 2 }"#
+        );
+        assert_eq!(rx.recv(), Some(1));
+    }
+
+    #[test]
+    fn builds_rendezvous_channel() {
+        let (tx, mut rx) = ChannelBuilder::new().capacity(0).build();
+        let sender = thread::spawn(move || tx.send(1));
+        assert_eq!(rx.recv(), Some(1));
+        sender.join().unwrap().unwrap();
+        assert_eq!(rx.name(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "a rendezvous channel can only block")]
+    fn rendezvous_rejects_lossy_policy() {
+        ChannelBuilder::<i32>::new()
+            .capacity(0)
+            .overflow(OverflowPolicy::DropNewest)
+            .build();
+    }
+}
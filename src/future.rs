@@ -0,0 +1,389 @@
+//! Async sending and receiving, independent of any runtime: a pending
+//! operation leaves its task's `Waker` in the channel, next to the `Condvar`
+//! blocking callers wait on, and whichever side makes progress wakes both.
+
+use crate::{QueueStorage, Receiver, Room, SendError, Sender, lock};
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+impl<T, S: QueueStorage<Item = T>> Receiver<T, S> {
+    /// Receives a value without blocking the thread. The future resolves to
+    /// `None` once every sender is gone and the channel is empty.
+    pub fn recv_async(&mut self) -> RecvFuture<'_, T, S> {
+        RecvFuture { receiver: self }
+    }
+
+    /// Polls for a value, registering `cx`'s waker if none is ready.
+    pub fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        if let Some(value) = self.pop_buffered() {
+            return Poll::Ready(Some(value));
+        }
+        let mut shared = lock(&self.inner.shared);
+        let value = self.inner.take(&mut shared, Some(&mut self.buffer));
+        if value.is_none() && !shared.is_disconnected() {
+            shared
+                .recv_wakers
+                .register_or_update(&mut self.waker_id, cx.waker());
+            return Poll::Pending;
+        }
+        if let Some(id) = self.waker_id.take() {
+            shared.recv_wakers.unregister(id);
+        }
+        Poll::Ready(value)
+    }
+}
+
+/// Future returned by [`Receiver::recv_async`].
+pub struct RecvFuture<'a, T, S: QueueStorage<Item = T>> {
+    receiver: &'a mut Receiver<T, S>,
+}
+
+impl<T, S: QueueStorage<Item = T>> Future for RecvFuture<'_, T, S> {
+    type Output = Option<T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+impl<T, S: QueueStorage<Item = T>> Drop for RecvFuture<'_, T, S> {
+    fn drop(&mut self) {
+        if let Some(id) = self.receiver.waker_id.take() {
+            let mut shared = lock(&self.receiver.inner.shared);
+            shared.recv_wakers.unregister(id);
+        }
+    }
+}
+
+impl<T, S: QueueStorage<Item = T>> Sender<T, S> {
+    /// Sends a value without blocking the thread, waiting for room on a
+    /// bounded channel and for the hand-off on a rendezvous one.
+    ///
+    /// Async and blocking senders are woken by the same receive, so neither
+    /// kind is starved by the other.
+    pub fn send_async(&self, value: T) -> SendFuture<'_, T, S> {
+        SendFuture {
+            sender: self,
+            value: Some(value),
+            ticket: None,
+            waker_id: None,
+        }
+    }
+}
+
+/// Future returned by [`Sender::send_async`].
+///
+/// Dropping it once a rendezvous value has been queued does not take the
+/// value back; it is still delivered.
+pub struct SendFuture<'a, T, S: QueueStorage<Item = T>> {
+    sender: &'a Sender<T, S>,
+    value: Option<T>,
+    /// Set once a rendezvous value is queued: the receive count at which it
+    /// has been taken.
+    ticket: Option<usize>,
+    /// Registration in `send_wakers` while pending.
+    waker_id: Option<usize>,
+}
+
+// The value is moved out, never pinned in place.
+impl<T, S: QueueStorage<Item = T>> Unpin for SendFuture<'_, T, S> {}
+
+impl<T, S: QueueStorage<Item = T>> Future for SendFuture<'_, T, S> {
+    type Output = Result<(), SendError<T>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let inner = &this.sender.inner;
+        let mut evicted = S::default();
+        let mut shared = lock(&inner.shared);
+        let result = match this.ticket {
+            Some(ticket) if shared.received >= ticket => Ok(()),
+            Some(_) if shared.closed => {
+                // Nobody took it, so ours is the only value queued.
+                Err(SendError(shared.queue.pop().expect("rendezvous value")))
+            }
+            Some(_) => {
+                shared
+                    .send_wakers
+                    .register_or_update(&mut this.waker_id, cx.waker());
+                return Poll::Pending;
+            }
+            None => match inner.make_room(&mut shared, &mut evicted) {
+                None => {
+                    shared
+                        .send_wakers
+                        .register_or_update(&mut this.waker_id, cx.waker());
+                    return Poll::Pending;
+                }
+                Some(room) => {
+                    let value = this.value.take().expect("polled after completion");
+                    match room {
+                        Room::Refused => Err(SendError(value)),
+                        Room::Discard => {
+                            evicted.push(value);
+                            Ok(())
+                        }
+                        Room::Free => {
+                            inner.push(&mut shared, value, S::push);
+                            if inner.capacity == Some(0) {
+                                this.ticket = Some(shared.received + shared.queue.len());
+                                shared
+                                    .send_wakers
+                                    .register_or_update(&mut this.waker_id, cx.waker());
+                                return Poll::Pending;
+                            }
+                            Ok(())
+                        }
+                    }
+                }
+            },
+        };
+        if let Some(id) = this.waker_id.take() {
+            shared.send_wakers.unregister(id);
+        }
+        Poll::Ready(result)
+    }
+}
+
+impl<T, S: QueueStorage<Item = T>> Drop for SendFuture<'_, T, S> {
+    fn drop(&mut self) {
+        if let Some(id) = self.waker_id.take() {
+            let mut shared = lock(&self.sender.inner.shared);
+            shared.send_wakers.unregister(id);
+        }
+    }
+}
+
+/// `poll_ready` reserves a slot, so the following `start_send` never
+/// overfills a bounded channel. Values are queued on `start_send`; on a
+/// rendezvous channel the sink does not wait for the hand-off.
+#[cfg(feature = "futures")]
+impl<T, S: QueueStorage<Item = T>> futures_sink::Sink<T> for Sender<T, S> {
+    type Error = SendError<T>;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        if this.reserved || this.inner.overflow != crate::OverflowPolicy::Block {
+            // Without blocking, `start_send` applies the policy instead.
+            return Poll::Ready(Ok(()));
+        }
+        let mut shared = lock(&this.inner.shared);
+        if !this.inner.can_send(&shared) {
+            shared
+                .send_wakers
+                .register_or_update(&mut this.waker_id, cx.waker());
+            return Poll::Pending;
+        }
+        if let Some(id) = this.waker_id.take() {
+            shared.send_wakers.unregister(id);
+        }
+        // A closed channel is "ready" too: `start_send` hands the value back.
+        if !shared.closed {
+            shared.reserved += 1;
+            this.reserved = true;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: T) -> Result<(), Self::Error> {
+        let this = self.get_mut();
+        let mut evicted = S::default();
+        let mut shared = lock(&this.inner.shared);
+        if this.reserved {
+            this.reserved = false;
+            shared.reserved -= 1;
+        }
+        match this.inner.make_room(&mut shared, &mut evicted) {
+            Some(Room::Refused) => Err(SendError(item)),
+            Some(Room::Discard) => {
+                evicted.push(item);
+                Ok(())
+            }
+            // Only a sink that skipped `poll_ready` finds no room.
+            Some(Room::Free) | None => {
+                this.inner.push(&mut shared, item, S::push);
+                Ok(())
+            }
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(feature = "futures")]
+impl<T, S: QueueStorage<Item = T> + Unpin> futures_core::Stream for Receiver<T, S> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.poll_recv(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        OverflowPolicy, SendError, channel, rendezvous, sync_channel, sync_channel_with_policy,
+    };
+    use std::future::Future;
+    use std::pin::pin;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::task::{Context, Poll, Wake, Waker};
+    use std::thread::{self, Thread};
+    use std::time::Duration;
+
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    /// Minimal executor: polls on the current thread, parking while pending.
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = pin!(future);
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(output) => return output,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+
+    #[test]
+    fn recv_async_from_sync_sender() {
+        let (tx, mut rx) = channel();
+        thread::spawn(move || {
+            for i in 0..3 {
+                thread::sleep(Duration::from_millis(5));
+                tx.send(i).unwrap();
+            }
+        });
+        let received = block_on(async {
+            let mut received = Vec::new();
+            while let Some(value) = rx.recv_async().await {
+                received.push(value);
+            }
+            received
+        });
+        assert_eq!(received, [0, 1, 2]);
+    }
+
+    /// Counts how often it was woken.
+    #[derive(Default)]
+    struct CountingWaker(AtomicUsize);
+
+    impl Wake for CountingWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn dropped_recv_async_is_not_woken() {
+        let (tx, mut rx) = channel();
+        let counter = Arc::new(CountingWaker::default());
+        let waker = Waker::from(Arc::clone(&counter));
+        {
+            let mut future = pin!(rx.recv_async());
+            let poll = future.as_mut().poll(&mut Context::from_waker(&waker));
+            assert_eq!(poll, Poll::Pending);
+        }
+        tx.send(1).unwrap();
+        assert_eq!(counter.0.load(Ordering::SeqCst), 0);
+        assert_eq!(rx.recv(), Some(1));
+    }
+
+    #[cfg(feature = "futures")]
+    #[test]
+    fn stream() {
+        use futures_core::Stream;
+        use std::future::poll_fn;
+        use std::pin::Pin;
+
+        let (tx, mut rx) = channel();
+        thread::spawn(move || tx.send(1).unwrap());
+        assert_eq!(
+            block_on(poll_fn(|cx| Pin::new(&mut rx).poll_next(cx))),
+            Some(1)
+        );
+        assert_eq!(
+            block_on(poll_fn(|cx| Pin::new(&mut rx).poll_next(cx))),
+            None
+        );
+    }
+
+    #[test]
+    fn send_async_waits_for_room() {
+        let (tx, rx) = sync_channel(1);
+        let handle = thread::spawn(move || {
+            block_on(async {
+                for i in 0..3 {
+                    tx.send_async(i).await.unwrap();
+                }
+                tx.send(3).unwrap();
+            })
+        });
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(rx.collect::<Vec<_>>(), [0, 1, 2, 3]);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn send_async_rendezvous() {
+        let (tx, mut rx) = rendezvous();
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            let first = rx.recv();
+            drop(rx);
+            first
+        });
+        assert_eq!(block_on(tx.send_async(1)), Ok(()));
+        assert_eq!(handle.join().unwrap(), Some(1));
+        assert_eq!(block_on(tx.send_async(2)), Err(SendError(2)));
+    }
+
+    #[cfg(feature = "futures")]
+    #[test]
+    fn sink() {
+        use futures_sink::Sink;
+        use std::future::poll_fn;
+        use std::pin::Pin;
+
+        let (mut tx, mut rx) = sync_channel(1);
+        let handle = thread::spawn(move || rx.by_ref().collect::<Vec<_>>());
+        block_on(async {
+            for i in 0..3 {
+                poll_fn(|cx| Pin::new(&mut tx).poll_ready(cx))
+                    .await
+                    .unwrap();
+                Pin::new(&mut tx).start_send(i).unwrap();
+            }
+        });
+        drop(tx);
+        assert_eq!(handle.join().unwrap(), [0, 1, 2]);
+    }
+
+    #[test]
+    fn send_async_applies_overflow_policy() {
+        let (tx, mut rx) = sync_channel_with_policy(1, OverflowPolicy::DropOldest);
+        block_on(tx.send_async(1)).unwrap();
+        block_on(tx.send_async(2)).unwrap();
+        assert_eq!(rx.try_recv(), Ok(2));
+        assert_eq!(rx.dropped_count(), 1);
+
+        let (tx, _rx) = sync_channel_with_policy(1, OverflowPolicy::Reject);
+        block_on(tx.send_async(1)).unwrap();
+        assert_eq!(block_on(tx.send_async(2)), Err(SendError(2)));
+    }
+}
@@ -0,0 +1,106 @@
+//! Iterators borrowing a [`Receiver`], so it stays usable once they end.
+
+use crate::{QueueStorage, Receiver};
+use std::time::Duration;
+
+impl<T, S: QueueStorage<Item = T>> Receiver<T, S> {
+    /// Iterates over received values, blocking for each one. Ends once
+    /// every sender is gone and the channel is empty.
+    ///
+    /// `for value in &mut receiver` does the same, since the receiver is
+    /// itself an iterator.
+    pub fn iter(&mut self) -> Iter<'_, T, S> {
+        Iter { receiver: self }
+    }
+
+    /// Iterates over the values ready right now, without blocking.
+    pub fn try_iter(&mut self) -> TryIter<'_, T, S> {
+        TryIter { receiver: self }
+    }
+
+    /// Iterates over received values, ending once none arrives for
+    /// `timeout` or the channel is disconnected.
+    pub fn iter_timeout(&mut self, timeout: Duration) -> TimeoutIter<'_, T, S> {
+        TimeoutIter {
+            receiver: self,
+            timeout,
+        }
+    }
+}
+
+/// Iterator returned by [`Receiver::iter`].
+pub struct Iter<'a, T, S: QueueStorage<Item = T>> {
+    receiver: &'a mut Receiver<T, S>,
+}
+
+impl<T, S: QueueStorage<Item = T>> Iterator for Iter<'_, T, S> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.receiver.recv()
+    }
+}
+
+/// Iterator returned by [`Receiver::try_iter`].
+pub struct TryIter<'a, T, S: QueueStorage<Item = T>> {
+    receiver: &'a mut Receiver<T, S>,
+}
+
+impl<T, S: QueueStorage<Item = T>> Iterator for TryIter<'_, T, S> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+/// Iterator returned by [`Receiver::iter_timeout`].
+pub struct TimeoutIter<'a, T, S: QueueStorage<Item = T>> {
+    receiver: &'a mut Receiver<T, S>,
+    timeout: Duration,
+}
+
+impl<T, S: QueueStorage<Item = T>> Iterator for TimeoutIter<'_, T, S> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.receiver.recv_timeout(self.timeout).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::channel;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn try_iter() {
+        let (tx, mut rx) = channel();
+        tx.send_all([1, 2, 3]).unwrap();
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), [1, 2, 3]);
+        assert_eq!(rx.try_iter().next(), None);
+        tx.send(4).unwrap();
+        assert_eq!(rx.recv(), Some(4));
+    }
+
+    #[test]
+    fn iter_timeout() {
+        let (tx, mut rx) = channel();
+        let sender = thread::spawn(move || {
+            tx.send(1).unwrap();
+            tx.send(2).unwrap();
+            thread::sleep(Duration::from_millis(200));
+            tx.send(3).unwrap();
+        });
+        let values: Vec<_> = rx.iter_timeout(Duration::from_millis(50)).collect();
+        assert_eq!(values, [1, 2]);
+        sender.join().unwrap();
+        let mut rest = vec![];
+        for value in &mut rx {
+            rest.push(value);
+        }
+        assert_eq!(rest, [3]);
+        assert_eq!(rx.iter().next(), None);
+    }
+}
@@ -1,5 +1,35 @@
-use std::collections::VecDeque;
-use std::sync::{Arc, Condvar, Mutex};
+use std::collections::{LinkedList, VecDeque};
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+pub mod array;
+pub mod block;
+pub mod broadcast;
+mod builder;
+mod error;
+mod future;
+mod iter;
+pub mod list;
+mod lockfree;
+pub mod mpmc;
+pub mod oneshot;
+mod parker;
+pub mod priority;
+mod select;
+mod storage;
+mod waker;
+pub mod watch;
+
+pub use builder::ChannelBuilder;
+pub use error::{RecvError, RecvTimeoutError, SendError, TryRecvError, TrySendError};
+pub use future::{RecvFuture, SendFuture};
+pub use iter::{Iter, TimeoutIter, TryIter};
+pub use select::Select;
+pub use storage::QueueStorage;
+use waker::Wakers;
 
 // Flavors:
 //  - Synchronous channels: Channel where send() can block. Limited capacity.
@@ -13,119 +43,753 @@ use std::sync::{Arc, Condvar, Mutex};
 //  - Rendezvous channels: Synchronous with capacity = 0. Used for thread synchronization.
 //  - Oneshot channels: Any capacity. In practice, only one call to send().
 
-pub struct Sender<T> {
-    inner: Arc<Inner<T>>,
+pub struct Sender<T, S: QueueStorage<Item = T> = VecDeque<T>> {
+    inner: Arc<Inner<T, S>>,
+    /// Registration in `send_wakers` while `Sink::poll_ready` is pending.
+    waker_id: Option<usize>,
+    /// Whether `Sink::poll_ready` holds a slot for the next `start_send`.
+    reserved: bool,
 }
 
-impl<T> Clone for Sender<T> {
+impl<T, S: QueueStorage<Item = T>> Clone for Sender<T, S> {
     fn clone(&self) -> Self {
-        let mut shared = self.inner.shared.lock().unwrap();
+        let mut shared = lock(&self.inner.shared);
         shared.senders += 1;
         drop(shared);
-        Self {
-            inner: Arc::clone(&self.inner),
-        }
+        Self::new(Arc::clone(&self.inner))
     }
 }
 
-impl<T> Drop for Sender<T> {
+impl<T, S: QueueStorage<Item = T>> Drop for Sender<T, S> {
     fn drop(&mut self) {
-        let mut shared = self.inner.shared.lock().unwrap();
+        let mut shared = lock(&self.inner.shared);
+        if let Some(id) = self.waker_id.take() {
+            shared.send_wakers.unregister(id);
+        }
+        if self.reserved {
+            self.inner.release(&mut shared);
+        }
         shared.senders -= 1;
         let is_last = shared.senders == 0;
+        if is_last {
+            shared.recv_wakers.wake_all();
+        }
         drop(shared);
         if is_last {
-            self.inner.available.notify_one()
+            self.inner.available.notify_all()
         }
     }
 }
 
-impl<T> Sender<T> {
-    pub fn send(&self, value: T) {
-        let mut shared = self.inner.shared.lock().unwrap();
-        shared.queue.push_back(value);
-        drop(shared);
-        self.inner.available.notify_one()
+impl<T, S: QueueStorage<Item = T>> fmt::Debug for Sender<T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.inner.fmt_fields(f.debug_struct("Sender"))
     }
 }
 
-pub struct Receiver<T> {
-    inner: Arc<Inner<T>>,
-    buffer: VecDeque<T>,
-}
+impl<T, S: QueueStorage<Item = T>> Sender<T, S> {
+    fn new(inner: Arc<Inner<T, S>>) -> Self {
+        Self {
+            inner,
+            waker_id: None,
+            reserved: false,
+        }
+    }
 
-impl<T> Receiver<T> {
-    pub fn recv(&mut self) -> Option<T> {
-        if let Some(value) = self.buffer.pop_front() {
-            return Some(value);
+    /// Closes the channel for every sender, e.g. for a coordinated
+    /// shutdown. The receiver still gets the values already queued.
+    pub fn close_channel(&self) {
+        let mut shared = lock(&self.inner.shared);
+        self.inner.close(&mut shared);
+    }
+
+    /// Number of values sent but not yet received, including the batch the
+    /// receiver has already taken off the shared queue.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The bound of a bounded channel, `Some(0)` for a rendezvous channel
+    /// and `None` for an unbounded one.
+    pub fn capacity(&self) -> Option<usize> {
+        self.inner.capacity
+    }
+
+    /// The name given by [`ChannelBuilder::name`], if any.
+    pub fn name(&self) -> Option<&str> {
+        self.inner.name.as_deref()
+    }
+
+    pub fn sender_count(&self) -> usize {
+        lock(&self.inner.shared).senders
+    }
+
+    /// Whether nothing more can be sent: the channel was closed, the
+    /// receiver is gone or every sender is.
+    pub fn is_closed(&self) -> bool {
+        lock(&self.inner.shared).is_disconnected()
+    }
+
+    /// Sends a value to the receiver, handing it back if the receiver is
+    /// gone or the channel was closed.
+    ///
+    /// On a bounded channel this blocks while the queue is full, until the
+    /// receiver makes room, unless the channel has another
+    /// [`OverflowPolicy`]. With [`OverflowPolicy::Reject`] the value is
+    /// handed back instead; use [`try_send`](Self::try_send) to tell that
+    /// apart from a disconnection. On a rendezvous channel it blocks until
+    /// the receiver has taken the value.
+    pub fn send(&self, value: T) -> Result<(), SendError<T>> {
+        self.send_by(value, S::push)
+    }
+
+    /// Sends a value without blocking, handing it back in
+    /// [`TrySendError::Full`] if the queue has no room for it, or in
+    /// [`TrySendError::Disconnected`] if the receiver is gone or the channel
+    /// was closed.
+    ///
+    /// A full channel with [`OverflowPolicy::DropOldest`] or
+    /// [`OverflowPolicy::DropNewest`] still applies its policy. A rendezvous
+    /// channel has no room to leave a value in, so this only ever fails on
+    /// one.
+    pub fn try_send(&self, value: T) -> Result<(), TrySendError<T>> {
+        let mut evicted = S::default();
+        let mut shared = lock(&self.inner.shared);
+        if self.inner.capacity == Some(0) {
+            return Err(shared.refuse(value));
         }
-        let mut shared = self.inner.shared.lock().unwrap();
-        loop {
-            match shared.queue.pop_front() {
-                Some(value) => {
-                    std::mem::swap(&mut self.buffer, &mut shared.queue);
-                    return Some(value);
+        match self.inner.make_room(&mut shared, &mut evicted) {
+            Some(Room::Free) => self.inner.push(&mut shared, value, S::push),
+            Some(Room::Refused) => return Err(shared.refuse(value)),
+            Some(Room::Discard) => evicted.push(value),
+            None => return Err(TrySendError::Full(value)),
+        }
+        Ok(())
+    }
+
+    /// Sends `value`, storing it with `push` once there is room.
+    fn send_by(&self, value: T, push: impl FnOnce(&mut S, T)) -> Result<(), SendError<T>> {
+        // Declared first so values evicted or discarded are dropped after
+        // unlocking.
+        let mut evicted = S::default();
+        let mut shared = lock(&self.inner.shared);
+        let room = loop {
+            if let Some(room) = self.inner.make_room(&mut shared, &mut evicted) {
+                break room;
+            }
+            shared = wait_until(&self.inner.space, shared, None).0;
+        };
+        match room {
+            Room::Free => self.inner.push(&mut shared, value, push),
+            Room::Refused => return Err(SendError(value)),
+            Room::Discard => evicted.push(value),
+        }
+        if self.inner.capacity == Some(0) {
+            let ticket = shared.received + shared.queue.len();
+            while shared.received < ticket {
+                if shared.closed {
+                    // Nobody took it, so ours is the only value queued.
+                    let value = shared.queue.pop().expect("rendezvous value");
+                    return Err(SendError(value));
                 }
-                None if shared.senders == 0 => return None,
-                None => {
-                    shared = self.inner.available.wait(shared).unwrap();
+                shared = wait_until(&self.inner.space, shared, None).0;
+            }
+        }
+        Ok(())
+    }
+
+    /// Sends every value of `values`, taking the lock and waking receivers
+    /// once rather than once per value. `values` is collected before the
+    /// lock is taken.
+    ///
+    /// On a bounded channel this blocks whenever the queue is full, letting
+    /// the receiver see what was queued so far, or applies the channel's
+    /// [`OverflowPolicy`]. If the channel closes or a value is rejected, that
+    /// value is handed back and the rest of `values` is dropped.
+    pub fn send_all(&self, values: impl IntoIterator<Item = T>) -> Result<(), SendError<T>> {
+        if self.inner.capacity == Some(0) {
+            // Every value is a hand-off of its own.
+            return values.into_iter().try_for_each(|value| self.send(value));
+        }
+        // Collected before locking: the iterator is the caller's code, which
+        // may use the channel itself or panic. Like `evicted`, declared
+        // before the guard so values left over are dropped after unlocking.
+        let mut values = values.into_iter().collect::<Vec<T>>().into_iter();
+        let mut evicted = S::default();
+        let mut shared = lock(&self.inner.shared);
+        let mut result = Ok(());
+        // Whether receivers were woken for the values queued since the last
+        // wait for room.
+        let mut woken = false;
+        for value in values.by_ref() {
+            let room = loop {
+                if let Some(room) = self.inner.make_room(&mut shared, &mut evicted) {
+                    break room;
                 }
+                woken = false;
+                shared = wait_until(&self.inner.space, shared, None).0;
+            };
+            match room {
+                Room::Free => {
+                    // Woken receivers only look once we unlock, so waking
+                    // ahead of the first push covers every way out of here.
+                    if !woken {
+                        self.inner.wake_receivers(&mut shared);
+                        woken = true;
+                    }
+                    shared.queue.push(value);
+                }
+                Room::Refused => {
+                    result = Err(SendError(value));
+                    break;
+                }
+                Room::Discard => evicted.push(value),
             }
         }
+        result
+    }
+}
+
+pub struct Receiver<T, S: QueueStorage<Item = T> = VecDeque<T>> {
+    inner: Arc<Inner<T, S>>,
+    buffer: S,
+    /// Registration in `recv_wakers` while an async receive is pending.
+    waker_id: Option<usize>,
+}
+
+impl<T, S: QueueStorage<Item = T>> Receiver<T, S> {
+    pub fn recv(&mut self) -> Option<T> {
+        self.recv_until(None).ok()
+    }
+
+    /// Blocks for at most `timeout` waiting for a value.
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        self.recv_until(deadline_after(timeout))
+    }
+
+    /// Blocks until `deadline` at the latest waiting for a value.
+    pub fn recv_deadline(&mut self, deadline: Instant) -> Result<T, RecvTimeoutError> {
+        self.recv_until(Some(deadline))
+    }
+
+    fn recv_until(&mut self, deadline: Option<Instant>) -> Result<T, RecvTimeoutError> {
+        if let Some(value) = self.pop_buffered() {
+            return Ok(value);
+        }
+        self.inner.recv_until(Some(&mut self.buffer), deadline)
+    }
+
+    /// Receives a value if one is ready, without blocking.
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        if let Some(value) = self.pop_buffered() {
+            return Ok(value);
+        }
+        self.inner.try_recv(Some(&mut self.buffer))
+    }
+
+    /// Blocks until a value is available, then returns it together with
+    /// whatever else is ready, up to `max` values in all. The batch is empty
+    /// once the channel is disconnected and drained.
+    pub fn recv_batch(&mut self, max: usize) -> Vec<T> {
+        let mut batch = Vec::new();
+        if max > 0
+            && let Some(value) = self.recv()
+        {
+            batch.push(value);
+            self.take_ready(&mut batch, max);
+        }
+        batch
+    }
+
+    /// Moves every value that is ready into `out` without blocking and
+    /// returns how many there were.
+    pub fn drain_into(&mut self, out: &mut Vec<T>) -> usize {
+        let len = out.len();
+        self.take_ready(out, usize::MAX);
+        out.len() - len
+    }
+
+    /// Moves ready values into `out` until it holds `limit` of them: the
+    /// local batch first, then the shared queue under a single lock.
+    fn take_ready(&mut self, out: &mut Vec<T>, limit: usize) {
+        while out.len() < limit
+            && let Some(value) = self.pop_buffered()
+        {
+            out.push(value);
+        }
+        if out.len() < limit {
+            let mut shared = lock(&self.inner.shared);
+            while out.len() < limit
+                && let Some(value) = self.inner.take(&mut shared, None)
+            {
+                out.push(value);
+            }
+        }
+    }
+
+    /// Closes the channel: further sends fail, while values already queued
+    /// can still be received. `recv` returns `None` once they are drained.
+    pub fn close(&self) {
+        let mut shared = lock(&self.inner.shared);
+        self.inner.close(&mut shared);
+    }
+
+    /// Number of values sent but not yet received, including the batch the
+    /// receiver has already taken off the shared queue.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The bound of a bounded channel, `Some(0)` for a rendezvous channel
+    /// and `None` for an unbounded one.
+    pub fn capacity(&self) -> Option<usize> {
+        self.inner.capacity
+    }
+
+    /// The name given by [`ChannelBuilder::name`], if any.
+    pub fn name(&self) -> Option<&str> {
+        self.inner.name.as_deref()
+    }
+
+    pub fn sender_count(&self) -> usize {
+        lock(&self.inner.shared).senders
+    }
+
+    /// Whether nothing more can be sent: the channel was closed, the
+    /// receiver is gone or every sender is.
+    pub fn is_closed(&self) -> bool {
+        lock(&self.inner.shared).is_disconnected()
+    }
+
+    /// Number of values lost to the channel's [`OverflowPolicy`], either
+    /// evicted or discarded on sending.
+    pub fn dropped_count(&self) -> usize {
+        lock(&self.inner.shared).dropped
+    }
+
+    /// Pops from the local batch, keeping `Inner::buffered` in step.
+    fn pop_buffered(&mut self) -> Option<T> {
+        let value = self.buffer.pop()?;
+        self.inner
+            .buffered
+            .store(self.buffer.len(), Ordering::Relaxed);
+        Some(value)
+    }
+}
+
+impl<T, S: QueueStorage<Item = T>> Drop for Receiver<T, S> {
+    fn drop(&mut self) {
+        if let Some(id) = self.waker_id.take() {
+            lock(&self.inner.shared).recv_wakers.unregister(id);
+        }
+        self.inner.drop_receiver();
     }
 }
 
-impl<T> Iterator for Receiver<T> {
+impl<T, S: QueueStorage<Item = T>> fmt::Debug for Receiver<T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.inner.fmt_fields(f.debug_struct("Receiver"))
+    }
+}
+
+impl<T, S: QueueStorage<Item = T>> Iterator for Receiver<T, S> {
     type Item = T;
     fn next(&mut self) -> Option<Self::Item> {
         self.recv()
     }
 }
 
-struct Inner<T> {
-    shared: Mutex<Shared<T>>,
+/// What a bounded channel does with a value sent while it is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverflowPolicy {
+    /// Wait for the receiver to make room.
+    #[default]
+    Block,
+    /// Fail, handing the value back: in [`TrySendError::Full`] from
+    /// [`Sender::try_send`], in a [`SendError`] from the other sends.
+    Reject,
+    /// Evict the value queued the longest to make room, as chosen by
+    /// [`QueueStorage::evict_oldest`].
+    DropOldest,
+    /// Discard the value sent; the send still succeeds.
+    DropNewest,
+}
+
+/// How a send can go on once it applied the overflow policy.
+enum Room {
+    Free,
+    /// The channel is closed or the value was rejected.
+    Refused,
+    /// The value is to be dropped.
+    Discard,
+}
+
+struct Inner<T, S> {
+    shared: Mutex<Shared<T, S>>,
     available: Condvar,
+    space: Condvar,
+    capacity: Option<usize>,
+    overflow: OverflowPolicy,
+    name: Option<String>,
+    /// Length of the receiver's local batch, so senders can report the
+    /// whole backlog without it.
+    buffered: AtomicUsize,
+}
+
+impl<T, S: QueueStorage<Item = T>> Inner<T, S> {
+    /// Pops the next queued value. On an unbounded channel a receiver with
+    /// a local buffer also takes the rest of the queue into it.
+    fn take(&self, shared: &mut Shared<T, S>, buffer: Option<&mut S>) -> Option<T> {
+        let value = shared.queue.pop()?;
+        match self.capacity {
+            // Taking the whole queue is only sound without a bound: otherwise
+            // senders could refill it while we still hold up to `capacity`
+            // values locally.
+            None => {
+                if let Some(buffer) = buffer {
+                    shared.queue.swap_out_all(buffer);
+                    self.buffered.store(buffer.len(), Ordering::Relaxed);
+                }
+            }
+            Some(capacity) => {
+                shared.received += 1;
+                shared.send_wakers.wake_all();
+                if capacity == 0 {
+                    // Both the handing-off sender and the ones waiting for
+                    // the slot sleep on `space`.
+                    self.space.notify_all();
+                } else {
+                    self.space.notify_one();
+                }
+            }
+        }
+        Some(value)
+    }
+
+    fn recv_until(
+        &self,
+        mut buffer: Option<&mut S>,
+        deadline: Option<Instant>,
+    ) -> Result<T, RecvTimeoutError> {
+        let mut shared = lock(&self.shared);
+        loop {
+            match self.take(&mut shared, buffer.as_deref_mut()) {
+                Some(value) => return Ok(value),
+                None if shared.is_disconnected() => return Err(RecvTimeoutError::Disconnected),
+                None => {
+                    let timed_out;
+                    (shared, timed_out) = wait_until(&self.available, shared, deadline);
+                    if timed_out {
+                        return Err(RecvTimeoutError::Timeout);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Shared by both halves' `Debug`, which leave the queue's values out.
+    fn fmt_fields(&self, mut f: fmt::DebugStruct<'_, '_>) -> fmt::Result {
+        f.field("name", &self.name)
+            .field("capacity", &self.capacity)
+            .field("overflow", &self.overflow)
+            .field("len", &self.len())
+            .finish()
+    }
+
+    fn len(&self) -> usize {
+        // Read `buffered` under the lock, which a swap into the receiver's
+        // batch holds while storing it, so no value is counted twice.
+        let shared = lock(&self.shared);
+        shared.queue.len() + self.buffered.load(Ordering::Relaxed)
+    }
+
+    /// Whether a receive would return right away, with a value or with a
+    /// disconnection.
+    fn can_recv(&self, shared: &Shared<T, S>) -> bool {
+        !shared.queue.is_empty() || shared.is_disconnected()
+    }
+
+    /// Whether a send would get past waiting for room, either to queue its
+    /// value or to fail on a closed channel. Slots reserved by `Sink`
+    /// senders count as taken.
+    fn can_send(&self, shared: &Shared<T, S>) -> bool {
+        match self.capacity {
+            // A rendezvous value still waits in the queue, one at a time.
+            Some(capacity) => {
+                shared.closed || shared.queue.len() + shared.reserved < capacity.max(1)
+            }
+            None => true,
+        }
+    }
+
+    /// Applies the overflow policy while the queue is full, moving evicted
+    /// values into `evicted` for the caller to drop after unlocking, along
+    /// with its own value on `Room::Discard`.
+    /// Returns `None` if the caller has to wait for room.
+    fn make_room(&self, shared: &mut Shared<T, S>, evicted: &mut S) -> Option<Room> {
+        while !self.can_send(shared) {
+            match self.overflow {
+                OverflowPolicy::Block => return None,
+                OverflowPolicy::Reject => return Some(Room::Refused),
+                OverflowPolicy::DropNewest => {
+                    shared.dropped += 1;
+                    return Some(Room::Discard);
+                }
+                OverflowPolicy::DropOldest => {
+                    // Empty only while `Sink` senders reserved every slot.
+                    evicted.push(shared.queue.evict_oldest()?);
+                    shared.dropped += 1;
+                }
+            }
+        }
+        Some(if shared.closed {
+            Room::Refused
+        } else {
+            Room::Free
+        })
+    }
+
+    /// Queues a value and wakes receivers. The caller checked for room.
+    fn push(&self, shared: &mut Shared<T, S>, value: T, push: impl FnOnce(&mut S, T)) {
+        push(&mut shared.queue, value);
+        shared.recv_wakers.wake_all();
+        self.available.notify_one();
+    }
+
+    /// Wakes every receiver after several values were queued at once.
+    fn wake_receivers(&self, shared: &mut Shared<T, S>) {
+        shared.recv_wakers.wake_all();
+        self.available.notify_all();
+    }
+
+    /// Hands back a slot reserved by `Sink::poll_ready` and not used.
+    fn release(&self, shared: &mut Shared<T, S>) {
+        shared.reserved -= 1;
+        shared.send_wakers.wake_all();
+        self.space.notify_one();
+    }
+
+    fn try_recv(&self, buffer: Option<&mut S>) -> Result<T, TryRecvError> {
+        let mut shared = lock(&self.shared);
+        match self.take(&mut shared, buffer) {
+            Some(value) => Ok(value),
+            None if shared.is_disconnected() => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    /// Makes further sends fail and wakes everyone waiting, so blocked
+    /// senders fail and receivers drain what is queued, then stop.
+    fn close(&self, shared: &mut Shared<T, S>) {
+        shared.closed = true;
+        shared.send_wakers.wake_all();
+        shared.recv_wakers.wake_all();
+        self.space.notify_all();
+        self.available.notify_all();
+    }
+
+    /// Closes the channel once the last receiver is gone, dropping whatever
+    /// is still queued and waking blocked senders.
+    fn drop_receiver(&self) {
+        let mut shared = lock(&self.shared);
+        shared.receivers -= 1;
+        if shared.receivers > 0 {
+            return;
+        }
+        self.close(&mut shared);
+        // A pending rendezvous value stays put for its sender to take back.
+        let queue = if self.capacity == Some(0) {
+            S::default()
+        } else {
+            std::mem::take(&mut shared.queue)
+        };
+        drop(shared);
+        drop(queue);
+    }
+}
+
+impl<T, S> Shared<T, S> {
+    /// Whether nothing more will be sent: every sender is gone or the
+    /// channel was closed.
+    fn is_disconnected(&self) -> bool {
+        self.senders == 0 || self.closed
+    }
+
+    /// The `try_send` error for a value refused by `Inner::make_room`.
+    fn refuse(&self, value: T) -> TrySendError<T> {
+        if self.closed {
+            TrySendError::Disconnected(value)
+        } else {
+            TrySendError::Full(value)
+        }
+    }
 }
 
-struct Shared<T> {
-    queue: VecDeque<T>,
+struct Shared<T, S> {
+    queue: S,
     senders: usize,
+    receivers: usize,
+    received: usize,
+    /// Slots promised to `Sink` senders between `poll_ready` and
+    /// `start_send`.
+    reserved: usize,
+    /// Values lost to the overflow policy.
+    dropped: usize,
+    closed: bool,
+    /// Woken whenever `can_recv` may have turned true.
+    recv_wakers: Wakers,
+    /// Woken whenever `can_send` may have turned true.
+    send_wakers: Wakers,
+    marker: PhantomData<T>,
 }
 
-pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
-    let inner = Arc::new(Inner {
-        shared: Mutex::new({
-            Shared {
-                queue: VecDeque::default(),
-                senders: 1,
+/// Locks `mutex`, recovering it if another thread panicked while holding
+/// it.
+///
+/// The channels finish their own bookkeeping before handing a value to
+/// user code, and drop the values they evict or discard after unlocking, so
+/// a poisoned lock still guards consistent state. Only a custom
+/// [`QueueStorage`] panicking halfway through its own update can break
+/// that. Recovering keeps one panicking user of a channel from taking down
+/// every other thread using it, least of all from a `Drop` running during
+/// unwinding.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// The deadline `timeout` from now, or none if that is too far out to
+/// represent.
+fn deadline_after(timeout: Duration) -> Option<Instant> {
+    Instant::now().checked_add(timeout)
+}
+
+/// Waits on `condvar` until notified or until `deadline` passes, recovering
+/// the lock like [`lock`].
+///
+/// The returned flag is only set when the deadline had already passed on
+/// entry, so callers re-check their condition after every wakeup, spurious
+/// or not, and give up on the following call.
+fn wait_until<'a, T>(
+    condvar: &Condvar,
+    guard: MutexGuard<'a, T>,
+    deadline: Option<Instant>,
+) -> (MutexGuard<'a, T>, bool) {
+    match deadline {
+        None => (
+            condvar.wait(guard).unwrap_or_else(PoisonError::into_inner),
+            false,
+        ),
+        Some(deadline) => {
+            let now = Instant::now();
+            if now >= deadline {
+                return (guard, true);
             }
-        }),
-        available: Condvar::new(),
-    });
-    (
-        Sender {
-            inner: inner.clone(),
-        },
-        Receiver {
-            inner,
-            buffer: VecDeque::new(),
-        },
-    )
+            (
+                condvar
+                    .wait_timeout(guard, deadline - now)
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .0,
+                false,
+            )
+        }
+    }
+}
+
+/// Creates an unbounded channel: `send` never blocks.
+pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+    ChannelBuilder::new().build()
+}
+
+/// Creates a bounded channel holding at most `capacity` values; `send`
+/// blocks while it is full.
+///
+/// # Panics
+///
+/// Panics if `capacity` is zero.
+pub fn sync_channel<T>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    assert!(capacity > 0, "sync_channel capacity must be non-zero");
+    ChannelBuilder::new().capacity(capacity).build()
+}
+
+/// Creates a bounded channel holding at most `capacity` values, where a
+/// send to a full channel follows `policy`. With a lossy policy this makes a
+/// ring buffer that never blocks, e.g. for telemetry.
+///
+/// # Panics
+///
+/// Panics if `capacity` is zero.
+pub fn sync_channel_with_policy<T>(
+    capacity: usize,
+    policy: OverflowPolicy,
+) -> (Sender<T>, Receiver<T>) {
+    assert!(capacity > 0, "sync_channel capacity must be non-zero");
+    ChannelBuilder::new()
+        .capacity(capacity)
+        .overflow(policy)
+        .build()
+}
+
+/// Creates a rendezvous channel: `send` blocks until the receiver takes the
+/// value, handing it off directly between threads.
+pub fn rendezvous<T>() -> (Sender<T>, Receiver<T>) {
+    ChannelBuilder::new().capacity(0).build()
+}
+
+/// Creates an unbounded channel over a `LinkedList`. Bursts then never
+/// reallocate and copy the whole queue the way a growing `VecDeque` does, at
+/// the cost of an allocation per value.
+pub fn linked_list_channel<T>() -> (Sender<T, LinkedList<T>>, Receiver<T, LinkedList<T>>) {
+    channel_with()
+}
+
+/// Creates an unbounded channel over any [`QueueStorage`], e.g.
+/// `channel_with::<LinkedList<u32>>()`.
+pub fn channel_with<S: QueueStorage>() -> (Sender<S::Item, S>, Receiver<S::Item, S>) {
+    ChannelBuilder::default().build()
+}
+
+/// Creates a bounded channel over any [`QueueStorage`], holding at most
+/// `capacity` values.
+///
+/// # Panics
+///
+/// Panics if `capacity` is zero.
+pub fn sync_channel_with<S: QueueStorage>(
+    capacity: usize,
+) -> (Sender<S::Item, S>, Receiver<S::Item, S>) {
+    assert!(capacity > 0, "sync_channel capacity must be non-zero");
+    ChannelBuilder::default().capacity(capacity).build()
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::channel;
+    use crate::{
+        OverflowPolicy, QueueStorage, RecvTimeoutError, SendError, TryRecvError, TrySendError,
+        channel, channel_with, linked_list_channel, rendezvous, sync_channel,
+        sync_channel_with_policy,
+    };
+    use std::collections::VecDeque;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
     use std::thread;
+    use std::time::{Duration, Instant};
 
     #[test]
     fn test_channel() {
         let (tx, mut rx) = channel();
         thread::spawn(move || {
-            tx.send(10);
-            tx.send(12);
-            tx.send(13);
-            tx.send(14);
+            tx.send(10).unwrap();
+            tx.send(12).unwrap();
+            tx.send(13).unwrap();
+            tx.send(14).unwrap();
         });
         assert_eq!(rx.recv(), Some(10));
         assert_eq!(rx.recv(), Some(12));
@@ -138,7 +802,7 @@ mod tests {
     fn drop_receiver() {
         let (tx, rx) = channel();
         drop(rx);
-        tx.send(1);
+        assert_eq!(tx.send(1), Err(SendError(1)));
     }
 
     #[test]
@@ -151,11 +815,367 @@ mod tests {
     #[test]
     fn recv_iterator() {
         let (tx, rx) = channel();
-        tx.send(1);
-        tx.send(2);
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
         drop(tx);
         for val in rx {
             assert!(val == 1 || val == 2);
         }
     }
+
+    #[test]
+    fn sync_channel_blocks_when_full() {
+        let (tx, mut rx) = sync_channel(2);
+        let sent = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&sent);
+        let handle = thread::spawn(move || {
+            for i in 0..4 {
+                tx.send(i).unwrap();
+                counter.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+        while sent.load(Ordering::SeqCst) < 2 {
+            thread::yield_now();
+        }
+        // The third send must stay blocked until a value is received.
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(sent.load(Ordering::SeqCst), 2);
+        assert_eq!(rx.recv(), Some(0));
+        assert_eq!(rx.recv(), Some(1));
+        assert_eq!(rx.recv(), Some(2));
+        assert_eq!(rx.recv(), Some(3));
+        assert_eq!(rx.recv(), None);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn rendezvous_waits_for_receiver() {
+        let (tx, mut rx) = rendezvous();
+        let sent = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&sent);
+        let handle = thread::spawn(move || {
+            tx.send(1).unwrap();
+            counter.fetch_add(1, Ordering::SeqCst);
+            tx.send(2).unwrap();
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(sent.load(Ordering::SeqCst), 0);
+        assert_eq!(rx.recv(), Some(1));
+        assert_eq!(rx.recv(), Some(2));
+        assert_eq!(rx.recv(), None);
+        assert_eq!(sent.load(Ordering::SeqCst), 2);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn drop_receiver_wakes_blocked_sender() {
+        let (tx, rx) = sync_channel(1);
+        tx.send(1).unwrap();
+        let handle = thread::spawn(move || tx.send(2));
+        thread::sleep(Duration::from_millis(50));
+        drop(rx);
+        assert_eq!(handle.join().unwrap(), Err(SendError(2)));
+    }
+
+    #[test]
+    fn drop_receiver_fails_rendezvous() {
+        let (tx, rx) = rendezvous();
+        let handle = thread::spawn(move || tx.send(1));
+        thread::sleep(Duration::from_millis(50));
+        drop(rx);
+        assert_eq!(handle.join().unwrap(), Err(SendError(1)));
+    }
+
+    #[test]
+    fn try_recv() {
+        let (tx, mut rx) = channel();
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+        assert_eq!(rx.try_recv(), Ok(1));
+        drop(tx);
+        assert_eq!(rx.try_recv(), Ok(2));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+    }
+
+    #[test]
+    fn recv_timeout() {
+        let (tx, mut rx) = channel();
+        let timeout = Duration::from_millis(20);
+        let start = Instant::now();
+        assert_eq!(rx.recv_timeout(timeout), Err(RecvTimeoutError::Timeout));
+        assert!(start.elapsed() >= timeout);
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(10));
+            tx.send(1).unwrap();
+        });
+        assert_eq!(rx.recv_timeout(Duration::from_secs(5)), Ok(1));
+        assert_eq!(
+            rx.recv_deadline(Instant::now() + Duration::from_secs(5)),
+            Err(RecvTimeoutError::Disconnected)
+        );
+    }
+
+    #[test]
+    fn linked_list_channel_keeps_order() {
+        let (tx, mut rx) = linked_list_channel();
+        thread::spawn(move || {
+            for i in 0..100 {
+                tx.send(i).unwrap();
+            }
+        });
+        assert_eq!(
+            rx.by_ref().collect::<Vec<_>>(),
+            (0..100).collect::<Vec<_>>()
+        );
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+    }
+
+    /// Storage that hands out the newest value first.
+    struct Stack<T>(Vec<T>);
+
+    impl<T> Default for Stack<T> {
+        fn default() -> Self {
+            Self(Vec::new())
+        }
+    }
+
+    impl<T> QueueStorage for Stack<T> {
+        type Item = T;
+
+        fn push(&mut self, value: T) {
+            self.0.push(value);
+        }
+
+        fn pop(&mut self) -> Option<T> {
+            self.0.pop()
+        }
+
+        fn len(&self) -> usize {
+            self.0.len()
+        }
+    }
+
+    #[test]
+    fn custom_storage() {
+        let (tx, rx) = channel_with::<Stack<i32>>();
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+        tx.send(3).unwrap();
+        drop(tx);
+        assert_eq!(rx.collect::<Vec<_>>(), [3, 2, 1]);
+    }
+
+    #[test]
+    fn receiver_close_drains_queue() {
+        let (tx, mut rx) = channel();
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+        rx.close();
+        assert_eq!(tx.send(3), Err(SendError(3)));
+        assert_eq!(rx.recv(), Some(1));
+        assert_eq!(rx.recv(), Some(2));
+        assert_eq!(rx.recv(), None);
+    }
+
+    #[test]
+    fn sender_close_channel_wakes_everyone() {
+        let (tx, mut rx) = sync_channel(1);
+        let tx2 = tx.clone();
+        tx.send(1).unwrap();
+        let blocked = thread::spawn(move || tx2.send(2));
+        thread::sleep(Duration::from_millis(20));
+        tx.close_channel();
+        assert_eq!(blocked.join().unwrap(), Err(SendError(2)));
+        assert_eq!(rx.recv(), Some(1));
+        assert_eq!(rx.recv(), None);
+    }
+
+    #[test]
+    fn introspection() {
+        let (tx, mut rx) = channel();
+        assert_eq!(tx.capacity(), None);
+        assert!(rx.is_empty());
+        for i in 0..3 {
+            tx.send(i).unwrap();
+        }
+        assert_eq!(tx.len(), 3);
+        assert_eq!(rx.recv(), Some(0));
+        // The rest now sits in the receiver's local batch.
+        assert_eq!(tx.len(), 2);
+        assert_eq!(rx.len(), 2);
+        let tx2 = tx.clone();
+        assert_eq!(rx.sender_count(), 2);
+        drop(tx2);
+        assert_eq!(tx.sender_count(), 1);
+        assert!(!tx.is_closed());
+        rx.close();
+        assert!(tx.is_closed());
+        assert_eq!(sync_channel::<i32>(4).0.capacity(), Some(4));
+    }
+
+    #[test]
+    fn len_is_consistent_while_the_receiver_takes_a_batch() {
+        let (tx, mut rx) = channel();
+        let done = Arc::new(AtomicBool::new(false));
+        let observer = {
+            let (tx, done) = (tx.clone(), Arc::clone(&done));
+            thread::spawn(move || {
+                let mut max = 0;
+                while !done.load(Ordering::Relaxed) {
+                    max = max.max(tx.len());
+                }
+                max
+            })
+        };
+        for _ in 0..2000 {
+            tx.send_all(0..100).unwrap();
+            for i in 0..100 {
+                assert_eq!(rx.recv(), Some(i));
+            }
+        }
+        done.store(true, Ordering::Relaxed);
+        assert!(observer.join().unwrap() <= 100);
+    }
+
+    #[test]
+    fn batches() {
+        let (tx, mut rx) = channel();
+        tx.send_all(0..5).unwrap();
+        assert_eq!(rx.recv_batch(3), [0, 1, 2]);
+        tx.send_all([5, 6]).unwrap();
+        let mut out = vec![];
+        assert_eq!(rx.drain_into(&mut out), 4);
+        assert_eq!(out, [3, 4, 5, 6]);
+        assert_eq!(rx.drain_into(&mut out), 0);
+        drop(tx);
+        assert!(rx.recv_batch(3).is_empty());
+    }
+
+    #[test]
+    fn recv_timeout_beyond_representable_deadline() {
+        let (tx, mut rx) = channel();
+        tx.send(1).unwrap();
+        assert_eq!(rx.recv_timeout(Duration::MAX), Ok(1));
+        drop(tx);
+        assert_eq!(
+            rx.recv_timeout(Duration::MAX),
+            Err(RecvTimeoutError::Disconnected)
+        );
+    }
+
+    #[test]
+    fn send_all_iterator_may_use_channel() {
+        let (tx, mut rx) = channel();
+        tx.send(0).unwrap();
+        tx.send_all((0..2).map(|i| i + tx.len())).unwrap();
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), [0, 1, 2]);
+    }
+
+    #[test]
+    fn bounded_send_all() {
+        let (tx, mut rx) = sync_channel(2);
+        let sender = thread::spawn(move || tx.send_all(0..10));
+        let mut received = vec![];
+        loop {
+            let batch = rx.recv_batch(4);
+            if batch.is_empty() {
+                break;
+            }
+            received.extend(batch);
+        }
+        assert_eq!(received, (0..10).collect::<Vec<_>>());
+        assert!(sender.join().unwrap().is_ok());
+
+        let (tx, rx) = sync_channel(1);
+        drop(rx);
+        assert_eq!(tx.send_all([1, 2]), Err(SendError(1)));
+    }
+
+    #[test]
+    fn send_all_iterator_panic_queues_nothing() {
+        let (tx, mut rx) = channel();
+        let panicking = tx.clone();
+        let result = thread::spawn(move || {
+            panicking.send_all((0..3).map(|i| if i < 2 { i } else { panic!("bad value") }))
+        })
+        .join();
+        assert!(result.is_err());
+        tx.send(2).unwrap();
+        drop(tx);
+        // The iterator ran before anything was queued.
+        assert_eq!(rx.recv_batch(10), [2]);
+        assert_eq!(rx.recv(), None);
+    }
+
+    #[test]
+    fn overflow_policies() {
+        let (tx, mut rx) = sync_channel_with_policy(2, OverflowPolicy::Reject);
+        tx.send_all([1, 2]).unwrap();
+        assert_eq!(tx.send(3), Err(SendError(3)));
+        assert_eq!(tx.try_send(3), Err(TrySendError::Full(3)));
+        assert_eq!(rx.recv(), Some(1));
+        tx.send(4).unwrap();
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), [2, 4]);
+        assert_eq!(rx.dropped_count(), 0);
+
+        let (tx, mut rx) = sync_channel_with_policy(2, OverflowPolicy::DropOldest);
+        tx.send_all(0..5).unwrap();
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), [3, 4]);
+        assert_eq!(rx.dropped_count(), 3);
+
+        let (tx, mut rx) = sync_channel_with_policy(2, OverflowPolicy::DropNewest);
+        tx.send_all(0..5).unwrap();
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), [0, 1]);
+        assert_eq!(rx.dropped_count(), 3);
+    }
+
+    #[test]
+    fn try_send_never_blocks() {
+        let (tx, mut rx) = sync_channel(1);
+        tx.try_send(1).unwrap();
+        assert_eq!(tx.try_send(2), Err(TrySendError::Full(2)));
+        assert_eq!(rx.recv(), Some(1));
+        tx.try_send(3).unwrap();
+
+        let (tx, rx) = rendezvous();
+        assert_eq!(tx.try_send(1), Err(TrySendError::Full(1)));
+        drop(rx);
+        assert_eq!(tx.try_send(1), Err(TrySendError::Disconnected(1)));
+    }
+
+    /// Storage whose `push` panics on negative values, before queueing them.
+    #[derive(Default)]
+    struct NonNegative(VecDeque<i32>);
+
+    impl QueueStorage for NonNegative {
+        type Item = i32;
+
+        fn push(&mut self, value: i32) {
+            assert!(value >= 0, "negative value");
+            self.0.push_back(value);
+        }
+
+        fn pop(&mut self) -> Option<i32> {
+            self.0.pop_front()
+        }
+
+        fn len(&self) -> usize {
+            self.0.len()
+        }
+    }
+
+    #[test]
+    fn survives_panic_while_locked() {
+        let (tx, mut rx) = channel_with::<NonNegative>();
+        let receiver = thread::spawn(move || rx.recv_timeout(Duration::from_secs(5)));
+        // Give the receiver time to block first.
+        thread::sleep(Duration::from_millis(50));
+        let panicking = tx.clone();
+        // Poisons the lock, and drops `panicking` while unwinding.
+        assert!(thread::spawn(move || panicking.send(-1)).join().is_err());
+        tx.send(1).unwrap();
+        assert_eq!(receiver.join().unwrap(), Ok(1));
+    }
 }
@@ -0,0 +1,217 @@
+//! An unbounded channel over a lock-free linked list of values.
+//!
+//! This is a Michael–Scott style queue specialised to a single consumer
+//! (Vyukov's intrusive MPSC queue): senders append with a single swap of
+//! `tail`, and nodes are only ever freed by the receiver, so no hazard
+//! pointers or epochs are needed. The receiver parks only when the list is
+//! empty.
+
+use crate::lockfree::{self, Queue};
+use crate::{RecvTimeoutError, SendError, TryRecvError, deadline_after};
+use std::cell::UnsafeCell;
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, Ordering};
+use std::time::{Duration, Instant};
+
+pub struct Sender<T> {
+    inner: lockfree::Sender<Inner<T>>,
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> Sender<T> {
+    /// Sends a value to the receiver, handing it back if the receiver is
+    /// gone. Never blocks.
+    pub fn send(&self, value: T) -> Result<(), SendError<T>> {
+        let chan = &self.inner.chan;
+        if chan.is_receiver_dropped() {
+            return Err(SendError(value));
+        }
+        chan.queue.push(value);
+        chan.not_empty.unpark_all();
+        Ok(())
+    }
+}
+
+pub struct Receiver<T> {
+    inner: lockfree::Receiver<Inner<T>>,
+}
+
+impl<T> Receiver<T> {
+    pub fn recv(&mut self) -> Option<T> {
+        self.inner.recv_until(None).ok()
+    }
+
+    /// Blocks for at most `timeout` waiting for a value.
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        self.inner.recv_until(deadline_after(timeout))
+    }
+
+    /// Blocks until `deadline` at the latest waiting for a value.
+    pub fn recv_deadline(&mut self, deadline: Instant) -> Result<T, RecvTimeoutError> {
+        self.inner.recv_until(Some(deadline))
+    }
+
+    /// Receives a value if one is ready, without blocking.
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        self.inner.try_recv()
+    }
+}
+
+impl<T> Iterator for Receiver<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.recv()
+    }
+}
+
+struct Node<T> {
+    value: Option<T>,
+    next: AtomicPtr<Node<T>>,
+}
+
+impl<T> Node<T> {
+    fn new(value: Option<T>) -> *mut Self {
+        Box::into_raw(Box::new(Self {
+            value,
+            next: AtomicPtr::new(ptr::null_mut()),
+        }))
+    }
+}
+
+struct Inner<T> {
+    /// The already consumed node in front of the queue. Only the receiver
+    /// touches it.
+    head: UnsafeCell<*mut Node<T>>,
+    tail: AtomicPtr<Node<T>>,
+}
+
+// SAFETY: values move between threads but are only ever accessed by one
+// thread at a time, and `head` is confined to the single receiver.
+unsafe impl<T: Send> Send for Inner<T> {}
+unsafe impl<T: Send> Sync for Inner<T> {}
+
+impl<T> Inner<T> {
+    fn push(&self, value: T) {
+        let node = Node::new(Some(value));
+        let prev = self.tail.swap(node, Ordering::AcqRel);
+        // SAFETY: the receiver never frees a node whose `next` is unset, and
+        // `prev` stays unlinked until this store.
+        unsafe { (*prev).next.store(node, Ordering::Release) };
+    }
+}
+
+impl<T> Queue for Inner<T> {
+    type Item = T;
+
+    /// Unlinks the next value. A send that has swapped `tail` but not yet
+    /// linked its node reads as empty; it unparks the receiver once linked.
+    unsafe fn pop(&self) -> Option<T> {
+        unsafe {
+            let head = *self.head.get();
+            let next = (*head).next.load(Ordering::Acquire);
+            if next.is_null() {
+                return None;
+            }
+            *self.head.get() = next;
+            drop(Box::from_raw(head));
+            (*next).value.take()
+        }
+    }
+
+    unsafe fn is_empty(&self) -> bool {
+        unsafe { (**self.head.get()).next.load(Ordering::Acquire).is_null() }
+    }
+}
+
+impl<T> Drop for Inner<T> {
+    fn drop(&mut self) {
+        let mut node = *self.head.get_mut();
+        while !node.is_null() {
+            // SAFETY: with every handle gone all nodes are linked and ours.
+            let boxed = unsafe { Box::from_raw(node) };
+            node = boxed.next.load(Ordering::Relaxed);
+        }
+    }
+}
+
+/// Creates an unbounded lock-free channel.
+pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+    let stub = Node::new(None);
+    let (tx, rx) = lockfree::new(Inner {
+        head: UnsafeCell::new(stub),
+        tail: AtomicPtr::new(stub),
+    });
+    (Sender { inner: tx }, Receiver { inner: rx })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::channel;
+    use crate::{RecvTimeoutError, SendError, TryRecvError};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_channel() {
+        let (tx, mut rx) = channel();
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+        thread::spawn(move || {
+            for i in 0..3 {
+                tx.send(i).unwrap();
+            }
+        });
+        assert_eq!(rx.recv(), Some(0));
+        assert_eq!(rx.recv(), Some(1));
+        assert_eq!(rx.recv(), Some(2));
+        assert_eq!(rx.recv(), None);
+    }
+
+    #[test]
+    fn many_senders() {
+        let (tx, rx) = channel();
+        for t in 0..8 {
+            let tx = tx.clone();
+            thread::spawn(move || {
+                for i in 0..1000 {
+                    tx.send(t * 1000 + i).unwrap();
+                }
+            });
+        }
+        drop(tx);
+        let mut values: Vec<usize> = rx.collect();
+        values.sort();
+        assert_eq!(values, (0..8000).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn drop_receiver() {
+        let (tx, rx) = channel();
+        tx.send(String::from("queued")).unwrap();
+        drop(rx);
+        assert_eq!(
+            tx.send(String::from("late")),
+            Err(SendError(String::from("late")))
+        );
+    }
+
+    #[test]
+    fn recv_timeout() {
+        let (tx, mut rx) = channel::<i32>();
+        assert_eq!(
+            rx.recv_timeout(Duration::from_millis(10)),
+            Err(RecvTimeoutError::Timeout)
+        );
+        drop(tx);
+        assert_eq!(
+            rx.recv_timeout(Duration::from_millis(10)),
+            Err(RecvTimeoutError::Disconnected)
+        );
+    }
+}
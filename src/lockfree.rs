@@ -0,0 +1,137 @@
+//! Sender and receiver plumbing shared by the lock-free flavors
+//! ([`list`](crate::list), [`block`](crate::block) and
+//! [`array`](crate::array)).
+//!
+//! Each flavor supplies its queue through [`Queue`]; counting senders,
+//! noticing a dropped receiver and parking the receiver while the queue is
+//! empty live here once.
+
+use crate::parker::Parker;
+use crate::{RecvTimeoutError, TryRecvError};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Instant;
+
+/// The queue behind a lock-free channel, with a single consumer.
+pub(crate) trait Queue {
+    type Item;
+
+    /// Takes the next value, if one is ready.
+    ///
+    /// # Safety
+    ///
+    /// Must only be called by the single receiver.
+    unsafe fn pop(&self) -> Option<Self::Item>;
+
+    /// # Safety
+    ///
+    /// Must only be called by the single receiver.
+    unsafe fn is_empty(&self) -> bool;
+
+    /// Called after the receiver took a value.
+    fn popped(&self) {}
+
+    /// Called once the receiver is gone, before the queue is drained.
+    fn receiver_gone(&self) {}
+}
+
+pub(crate) struct Chan<Q> {
+    pub(crate) queue: Q,
+    senders: AtomicUsize,
+    receiver_dropped: AtomicBool,
+    /// Parks the receiver while the queue is empty.
+    pub(crate) not_empty: Parker,
+}
+
+impl<Q> Chan<Q> {
+    pub(crate) fn is_receiver_dropped(&self) -> bool {
+        self.receiver_dropped.load(Ordering::Acquire)
+    }
+}
+
+/// Creates the two handles of a channel over `queue`.
+pub(crate) fn new<Q: Queue>(queue: Q) -> (Sender<Q>, Receiver<Q>) {
+    let chan = Arc::new(Chan {
+        queue,
+        senders: AtomicUsize::new(1),
+        receiver_dropped: AtomicBool::new(false),
+        not_empty: Parker::new(),
+    });
+    (
+        Sender {
+            chan: Arc::clone(&chan),
+        },
+        Receiver { chan },
+    )
+}
+
+pub(crate) struct Sender<Q> {
+    pub(crate) chan: Arc<Chan<Q>>,
+}
+
+impl<Q> Clone for Sender<Q> {
+    fn clone(&self) -> Self {
+        self.chan.senders.fetch_add(1, Ordering::Relaxed);
+        Self {
+            chan: Arc::clone(&self.chan),
+        }
+    }
+}
+
+impl<Q> Drop for Sender<Q> {
+    fn drop(&mut self) {
+        if self.chan.senders.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.chan.not_empty.unpark_all();
+        }
+    }
+}
+
+pub(crate) struct Receiver<Q: Queue> {
+    chan: Arc<Chan<Q>>,
+}
+
+impl<Q: Queue> Receiver<Q> {
+    pub(crate) fn recv_until(
+        &mut self,
+        deadline: Option<Instant>,
+    ) -> Result<Q::Item, RecvTimeoutError> {
+        loop {
+            match self.try_recv() {
+                Ok(value) => return Ok(value),
+                Err(TryRecvError::Disconnected) => return Err(RecvTimeoutError::Disconnected),
+                Err(TryRecvError::Empty) => {}
+            }
+            let chan = &self.chan;
+            // SAFETY: `&mut self` makes this the only consumer.
+            let ready = chan.not_empty.park_until(deadline, || unsafe {
+                chan.queue.is_empty() && chan.senders.load(Ordering::Acquire) > 0
+            });
+            if !ready {
+                return Err(RecvTimeoutError::Timeout);
+            }
+        }
+    }
+
+    pub(crate) fn try_recv(&mut self) -> Result<Q::Item, TryRecvError> {
+        // SAFETY: `&mut self` makes this the only consumer.
+        let value = match unsafe { self.chan.queue.pop() } {
+            Some(value) => value,
+            None if self.chan.senders.load(Ordering::Acquire) == 0 => {
+                // The last sender may have sent right before dropping.
+                unsafe { self.chan.queue.pop() }.ok_or(TryRecvError::Disconnected)?
+            }
+            None => return Err(TryRecvError::Empty),
+        };
+        self.chan.queue.popped();
+        Ok(value)
+    }
+}
+
+impl<Q: Queue> Drop for Receiver<Q> {
+    fn drop(&mut self) {
+        self.chan.receiver_dropped.store(true, Ordering::Release);
+        self.chan.queue.receiver_gone();
+        // SAFETY: `&mut self` makes this the only consumer.
+        while unsafe { self.chan.queue.pop() }.is_some() {}
+    }
+}
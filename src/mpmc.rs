@@ -0,0 +1,169 @@
+//! A multi-consumer flavor of [`channel`](crate::channel): receivers can be
+//! cloned and each value goes to exactly one of them.
+//!
+//! Receivers pop one value per lock acquisition instead of swapping the
+//! whole queue into a local buffer, so an idle sibling never sits behind
+//! values another receiver has already claimed.
+
+use crate::{
+    ChannelBuilder, Inner, QueueStorage, RecvTimeoutError, Sender, TryRecvError, deadline_after,
+    lock,
+};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+pub struct Receiver<T, S: QueueStorage<Item = T> = VecDeque<T>> {
+    pub(crate) inner: Arc<Inner<T, S>>,
+}
+
+impl<T, S: QueueStorage<Item = T>> Clone for Receiver<T, S> {
+    fn clone(&self) -> Self {
+        let mut shared = lock(&self.inner.shared);
+        shared.receivers += 1;
+        drop(shared);
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<T, S: QueueStorage<Item = T>> Drop for Receiver<T, S> {
+    fn drop(&mut self) {
+        self.inner.drop_receiver();
+    }
+}
+
+impl<T, S: QueueStorage<Item = T>> Receiver<T, S> {
+    pub fn recv(&self) -> Option<T> {
+        self.inner.recv_until(None, None).ok()
+    }
+
+    /// Blocks for at most `timeout` waiting for a value.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        self.inner.recv_until(None, deadline_after(timeout))
+    }
+
+    /// Blocks until `deadline` at the latest waiting for a value.
+    pub fn recv_deadline(&self, deadline: Instant) -> Result<T, RecvTimeoutError> {
+        self.inner.recv_until(None, Some(deadline))
+    }
+
+    /// Receives a value if one is ready, without blocking.
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        self.inner.try_recv(None)
+    }
+
+    /// Closes the channel for every receiver: further sends fail, while
+    /// values already queued can still be received.
+    pub fn close(&self) {
+        let mut shared = lock(&self.inner.shared);
+        self.inner.close(&mut shared);
+    }
+
+    /// Number of values sent but not yet received by any receiver.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The bound of a bounded channel, `None` for an unbounded one.
+    pub fn capacity(&self) -> Option<usize> {
+        self.inner.capacity
+    }
+
+    pub fn sender_count(&self) -> usize {
+        lock(&self.inner.shared).senders
+    }
+
+    /// Whether nothing more can be sent: the channel was closed, every
+    /// receiver is gone or every sender is.
+    pub fn is_closed(&self) -> bool {
+        lock(&self.inner.shared).is_disconnected()
+    }
+}
+
+impl<T, S: QueueStorage<Item = T>> Iterator for Receiver<T, S> {
+    type Item = T;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.recv()
+    }
+}
+
+/// Creates an unbounded multi-consumer channel.
+pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+    new_channel(ChannelBuilder::new())
+}
+
+/// Creates a bounded multi-consumer channel holding at most `capacity`
+/// values.
+///
+/// # Panics
+///
+/// Panics if `capacity` is zero.
+pub fn sync_channel<T>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    assert!(capacity > 0, "sync_channel capacity must be non-zero");
+    new_channel(ChannelBuilder::new().capacity(capacity))
+}
+
+fn new_channel<T>(builder: ChannelBuilder<T>) -> (Sender<T>, Receiver<T>) {
+    let inner = builder.build_inner();
+    (Sender::new(inner.clone()), Receiver { inner })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{channel, sync_channel};
+    use crate::SendError;
+    use std::thread;
+
+    #[test]
+    fn work_is_shared_between_receivers() {
+        let (tx, rx) = channel();
+        let workers: Vec<_> = (0..4)
+            .map(|_| {
+                let rx = rx.clone();
+                thread::spawn(move || rx.sum::<usize>())
+            })
+            .collect();
+        drop(rx);
+        for i in 0..1000 {
+            tx.send(i).unwrap();
+        }
+        drop(tx);
+        let total: usize = workers.into_iter().map(|w| w.join().unwrap()).sum();
+        assert_eq!(total, (0..1000).sum());
+    }
+
+    #[test]
+    fn closed_after_last_receiver() {
+        let (tx, rx) = sync_channel(1);
+        let rx2 = rx.clone();
+        drop(rx);
+        tx.send(1).unwrap();
+        assert_eq!(rx2.recv(), Some(1));
+        drop(rx2);
+        assert_eq!(tx.send(2), Err(SendError(2)));
+    }
+
+    #[test]
+    fn introspection() {
+        let (tx, rx) = sync_channel(4);
+        assert_eq!(rx.capacity(), Some(4));
+        assert!(rx.is_empty());
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+        assert_eq!(rx.len(), 2);
+        let tx2 = tx.clone();
+        assert_eq!(rx.sender_count(), 2);
+        assert!(!rx.is_closed());
+        drop((tx, tx2));
+        assert!(rx.is_closed());
+        assert_eq!(rx.recv(), Some(1));
+        assert_eq!(rx.len(), 1);
+        assert_eq!(channel::<i32>().1.capacity(), None);
+    }
+}
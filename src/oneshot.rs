@@ -0,0 +1,115 @@
+//! A channel for sending exactly one value, typically the result of a
+//! spawned thread.
+
+use crate::{RecvError, SendError, lock, wait_until};
+use std::sync::{Arc, Condvar, Mutex};
+
+pub struct Sender<T> {
+    inner: Arc<Inner<T>>,
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut shared = lock(&self.inner.shared);
+        shared.sender_dropped = true;
+        drop(shared);
+        self.inner.available.notify_one()
+    }
+}
+
+impl<T> Sender<T> {
+    /// Sends the value, consuming the sender. Fails if the receiver is
+    /// gone, handing the value back.
+    pub fn send(self, value: T) -> Result<(), SendError<T>> {
+        let mut shared = lock(&self.inner.shared);
+        if shared.receiver_dropped {
+            return Err(SendError(value));
+        }
+        shared.value = Some(value);
+        Ok(())
+    }
+}
+
+pub struct Receiver<T> {
+    inner: Arc<Inner<T>>,
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let mut shared = lock(&self.inner.shared);
+        shared.receiver_dropped = true;
+    }
+}
+
+impl<T> Receiver<T> {
+    /// Blocks until the value arrives, or fails if the sender was dropped
+    /// without sending.
+    pub fn recv(self) -> Result<T, RecvError> {
+        let mut shared = lock(&self.inner.shared);
+        loop {
+            match shared.value.take() {
+                Some(value) => return Ok(value),
+                None if shared.sender_dropped => return Err(RecvError),
+                None => {
+                    shared = wait_until(&self.inner.available, shared, None).0;
+                }
+            }
+        }
+    }
+}
+
+struct Inner<T> {
+    shared: Mutex<Shared<T>>,
+    available: Condvar,
+}
+
+struct Shared<T> {
+    value: Option<T>,
+    sender_dropped: bool,
+    receiver_dropped: bool,
+}
+
+pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+    let inner = Arc::new(Inner {
+        shared: Mutex::new(Shared {
+            value: None,
+            sender_dropped: false,
+            receiver_dropped: false,
+        }),
+        available: Condvar::new(),
+    });
+    (
+        Sender {
+            inner: inner.clone(),
+        },
+        Receiver { inner },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::channel;
+    use crate::{RecvError, SendError};
+    use std::thread;
+
+    #[test]
+    fn send_from_thread() {
+        let (tx, rx) = channel();
+        thread::spawn(move || tx.send(42).unwrap());
+        assert_eq!(rx.recv(), Ok(42));
+    }
+
+    #[test]
+    fn drop_sender() {
+        let (tx, rx) = channel::<i32>();
+        drop(tx);
+        assert_eq!(rx.recv(), Err(RecvError));
+    }
+
+    #[test]
+    fn drop_receiver() {
+        let (tx, rx) = channel();
+        drop(rx);
+        assert_eq!(tx.send(1), Err(SendError(1)));
+    }
+}
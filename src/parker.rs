@@ -0,0 +1,111 @@
+//! A list of parked threads, used by the atomic flavors in place of a
+//! `Condvar`.
+//!
+//! Its `Mutex` is only taken by a thread about to park and by a notifier
+//! that found someone registered, so sends and receives that never wait
+//! stay lock-free.
+
+use crate::lock;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering, fence};
+use std::thread::{self, Thread};
+use std::time::Instant;
+
+pub(crate) struct Parker {
+    waiters: Mutex<Vec<Thread>>,
+    /// `waiters.len()`, readable without the lock.
+    registered: AtomicUsize,
+}
+
+impl Parker {
+    pub(crate) fn new() -> Self {
+        Self {
+            waiters: Mutex::new(Vec::new()),
+            registered: AtomicUsize::new(0),
+        }
+    }
+
+    /// Parks the current thread while `blocked` returns true, giving up once
+    /// `deadline` passes. Returns whether `blocked` was observed false.
+    ///
+    /// The thread registers itself once, before re-checking `blocked`, and
+    /// stays registered until it returns, so a state change followed by
+    /// [`unpark_all`](Self::unpark_all) is never missed and spurious
+    /// wakeups cost nothing extra.
+    pub(crate) fn park_until(
+        &self,
+        deadline: Option<Instant>,
+        mut blocked: impl FnMut() -> bool,
+    ) -> bool {
+        if !blocked() {
+            return true;
+        }
+        self.register();
+        let ready = loop {
+            // Pairs with the fence in `unpark_all`: either we see the new
+            // state, or the notifier sees our registration.
+            fence(Ordering::SeqCst);
+            if !blocked() {
+                break true;
+            }
+            match deadline {
+                None => thread::park(),
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        break false;
+                    }
+                    thread::park_timeout(deadline - now);
+                }
+            }
+        };
+        self.deregister();
+        ready
+    }
+
+    /// Unparks every registered thread. Call after publishing the state
+    /// change they may be waiting for.
+    pub(crate) fn unpark_all(&self) {
+        fence(Ordering::SeqCst);
+        if self.registered.load(Ordering::Relaxed) == 0 {
+            return;
+        }
+        // Waiters deregister themselves, so an unpark reaching one that is
+        // already on its way out only leaves it a stale token, which every
+        // `park` caller tolerates.
+        for waiter in lock(&self.waiters).iter() {
+            waiter.unpark();
+        }
+    }
+
+    fn register(&self) {
+        let mut waiters = lock(&self.waiters);
+        waiters.push(thread::current());
+        self.registered.store(waiters.len(), Ordering::Relaxed);
+    }
+
+    fn deregister(&self) {
+        let id = thread::current().id();
+        let mut waiters = lock(&self.waiters);
+        if let Some(index) = waiters.iter().position(|waiter| waiter.id() == id) {
+            waiters.swap_remove(index);
+        }
+        self.registered.store(waiters.len(), Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Parker;
+    use crate::lock;
+    use std::time::Instant;
+
+    #[test]
+    fn timed_out_waits_leave_no_registration() {
+        let parker = Parker::new();
+        for _ in 0..1000 {
+            assert!(!parker.park_until(Some(Instant::now()), || true));
+        }
+        assert!(lock(&parker.waiters).is_empty());
+    }
+}
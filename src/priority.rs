@@ -0,0 +1,160 @@
+//! A channel that delivers the highest-priority value first, and values of
+//! equal priority in the order they were sent.
+
+use crate::{QueueStorage, Receiver, SendError, Sender};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap};
+
+/// [`QueueStorage`] ordering values by priority, then by arrival.
+///
+/// Plain [`Sender::send`] uses priority `0`, the lowest.
+pub struct PriorityQueue<T> {
+    /// Queued values, by arrival.
+    values: BTreeMap<u64, T>,
+    /// Priority and arrival of every queued value, highest priority and
+    /// earliest arrival on top. Evicted values leave their key behind until
+    /// it reaches the top or the stale keys outnumber the queued ones.
+    order: BinaryHeap<(u32, Reverse<u64>)>,
+    next_seq: u64,
+}
+
+impl<T> PriorityQueue<T> {
+    pub fn push_with_priority(&mut self, value: T, priority: u32) {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.values.insert(seq, value);
+        self.order.push((priority, Reverse(seq)));
+    }
+}
+
+impl<T> Default for PriorityQueue<T> {
+    fn default() -> Self {
+        Self {
+            values: BTreeMap::new(),
+            order: BinaryHeap::new(),
+            next_seq: 0,
+        }
+    }
+}
+
+impl<T> QueueStorage for PriorityQueue<T> {
+    type Item = T;
+
+    fn push(&mut self, value: T) {
+        self.push_with_priority(value, 0);
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        while let Some((_, Reverse(seq))) = self.order.pop() {
+            if let Some(value) = self.values.remove(&seq) {
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    fn with_capacity(capacity: usize) -> Self {
+        Self {
+            order: BinaryHeap::with_capacity(capacity),
+            ..Self::default()
+        }
+    }
+
+    /// Evicts by arrival, not priority, so a full lossy channel keeps its
+    /// urgent values. Amortised logarithmic: the heap is only swept once
+    /// half of it is stale.
+    fn evict_oldest(&mut self) -> Option<T> {
+        let (_, value) = self.values.pop_first()?;
+        if self.order.len() > 2 * self.values.len() {
+            let values = &self.values;
+            self.order
+                .retain(|(_, Reverse(seq))| values.contains_key(seq));
+        }
+        Some(value)
+    }
+
+    /// Keeps everything in the shared queue: a batch taken now would be
+    /// overtaken by more urgent values sent while it is being drained.
+    fn swap_out_all(&mut self, _buffer: &mut Self) {}
+}
+
+impl<T> Sender<T, PriorityQueue<T>> {
+    /// Sends a value that is received before any value of lower priority.
+    pub fn send_with_priority(&self, value: T, priority: u32) -> Result<(), SendError<T>> {
+        self.send_by(value, |queue, value| {
+            queue.push_with_priority(value, priority)
+        })
+    }
+}
+
+/// Creates an unbounded priority channel.
+pub fn channel<T>() -> (Sender<T, PriorityQueue<T>>, Receiver<T, PriorityQueue<T>>) {
+    crate::channel_with()
+}
+
+/// Creates a bounded priority channel holding at most `capacity` values.
+///
+/// # Panics
+///
+/// Panics if `capacity` is zero.
+pub fn sync_channel<T>(
+    capacity: usize,
+) -> (Sender<T, PriorityQueue<T>>, Receiver<T, PriorityQueue<T>>) {
+    crate::sync_channel_with(capacity)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PriorityQueue, channel};
+    use crate::{ChannelBuilder, OverflowPolicy, QueueStorage};
+
+    #[test]
+    fn highest_priority_first() {
+        let (tx, mut rx) = channel();
+        tx.send("bulk 1").unwrap();
+        tx.send_with_priority("urgent 1", 5).unwrap();
+        tx.send("bulk 2").unwrap();
+        tx.send_with_priority("normal", 1).unwrap();
+        tx.send_with_priority("urgent 2", 5).unwrap();
+        assert_eq!(rx.recv(), Some("urgent 1"));
+        // Values sent after the first receive still overtake queued ones.
+        tx.send_with_priority("urgent 3", 5).unwrap();
+        drop(tx);
+        assert_eq!(
+            rx.collect::<Vec<_>>(),
+            ["urgent 2", "urgent 3", "normal", "bulk 1", "bulk 2"]
+        );
+    }
+
+    #[test]
+    fn drop_oldest_keeps_urgent_values() {
+        let (tx, rx) = ChannelBuilder::new()
+            .capacity(2)
+            .overflow(OverflowPolicy::DropOldest)
+            .backend::<PriorityQueue<_>>()
+            .build();
+        tx.send("bulk-old").unwrap();
+        tx.send_with_priority("URGENT", 9).unwrap();
+        tx.send("bulk-new").unwrap();
+        drop(tx);
+        assert_eq!(rx.collect::<Vec<_>>(), ["URGENT", "bulk-new"]);
+    }
+
+    #[test]
+    fn eviction_sweeps_stale_keys() {
+        let mut queue = PriorityQueue::default();
+        for i in 0..1000 {
+            queue.push_with_priority(i, i % 3);
+            if queue.len() > 4 {
+                assert_eq!(queue.evict_oldest(), Some(i - 4));
+            }
+            assert!(queue.order.len() <= 2 * queue.len());
+        }
+        let left: Vec<_> = std::iter::from_fn(|| queue.pop()).collect();
+        assert_eq!(left, [998, 997, 996, 999]);
+    }
+}
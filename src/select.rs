@@ -0,0 +1,314 @@
+use crate::{QueueStorage, Receiver, Sender, deadline_after, lock, mpmc, wait_until};
+use std::cell::Cell;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Wake, Waker};
+use std::time::{Duration, Instant};
+
+/// Waits on several channel operations at once.
+///
+/// Register receivers and senders, then call [`ready`](Self::ready) to block
+/// until one of them can proceed without waiting. It returns the index that
+/// operation was given at registration; perform it afterwards with
+/// `try_recv` or `send`. A receiver counts as ready once it has a value or
+/// every sender is gone; a sender once its bounded queue has room or the
+/// receiver is gone. On a rendezvous channel a ready sender has a free
+/// hand-off slot but still waits for the receiver to take its value.
+///
+/// ```
+/// use falgu_rs::{Select, channel};
+///
+/// let (commands_tx, mut commands) = channel::<&str>();
+/// let (shutdown_tx, mut shutdown) = channel::<()>();
+/// shutdown_tx.send(()).unwrap();
+///
+/// let mut select = Select::new();
+/// let on_command = select.recv(&commands);
+/// let on_shutdown = select.recv(&shutdown);
+/// let index = select.ready();
+/// if index == on_shutdown {
+///     assert_eq!(shutdown.try_recv(), Ok(()));
+/// } else if index == on_command {
+///     commands.try_recv().unwrap();
+/// }
+/// # drop(commands_tx);
+/// ```
+#[derive(Default)]
+pub struct Select<'a> {
+    handles: Vec<&'a dyn Selectable>,
+}
+
+impl<'a> Select<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Waits for `receiver` to have a value. Returns its index.
+    pub fn recv<T, S: QueueStorage<Item = T>>(&mut self, receiver: &'a Receiver<T, S>) -> usize {
+        self.push(receiver)
+    }
+
+    /// Waits for one of the receivers of a multi-consumer channel to be
+    /// able to take a value. Returns its index.
+    pub fn recv_mpmc<T, S: QueueStorage<Item = T>>(
+        &mut self,
+        receiver: &'a mpmc::Receiver<T, S>,
+    ) -> usize {
+        self.push(receiver)
+    }
+
+    /// Waits for `sender` to have room. Returns its index.
+    pub fn send<T, S: QueueStorage<Item = T>>(&mut self, sender: &'a Sender<T, S>) -> usize {
+        self.push(sender)
+    }
+
+    fn push(&mut self, handle: &'a dyn Selectable) -> usize {
+        self.handles.push(handle);
+        self.handles.len() - 1
+    }
+
+    /// Returns a ready operation, without blocking.
+    ///
+    /// The scan starts at a random operation, so a busy channel cannot
+    /// starve those registered after it, even when a `Select` is built
+    /// afresh for every receive.
+    pub fn try_ready(&self) -> Option<usize> {
+        let len = self.handles.len();
+        if len == 0 {
+            return None;
+        }
+        let start = random_below(len);
+        (start..start + len)
+            .map(|index| index % len)
+            .find(|&index| self.handles[index].is_ready())
+    }
+
+    /// Blocks until an operation is ready.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no operation was registered.
+    pub fn ready(&self) -> usize {
+        self.ready_until(None).expect("no deadline")
+    }
+
+    /// Blocks for at most `timeout` until an operation is ready.
+    pub fn ready_timeout(&self, timeout: Duration) -> Option<usize> {
+        self.ready_until(deadline_after(timeout))
+    }
+
+    /// Blocks until `deadline` at the latest until an operation is ready.
+    pub fn ready_deadline(&self, deadline: Instant) -> Option<usize> {
+        self.ready_until(Some(deadline))
+    }
+
+    fn ready_until(&self, deadline: Option<Instant>) -> Option<usize> {
+        assert!(!self.handles.is_empty(), "no operations to select on");
+        if let Some(index) = self.try_ready() {
+            return Some(index);
+        }
+        let signal = Arc::new(Signal::default());
+        let waker = Waker::from(Arc::clone(&signal));
+        let ids: Vec<_> = self
+            .handles
+            .iter()
+            .map(|handle| handle.register(&waker))
+            .collect();
+        let ready = loop {
+            // Registered first, so a change after this check wakes `signal`.
+            if let Some(index) = self.try_ready() {
+                break Some(index);
+            }
+            if !signal.wait(deadline) {
+                break self.try_ready();
+            }
+        };
+        for (handle, id) in self.handles.iter().zip(ids) {
+            handle.unregister(id);
+        }
+        ready
+    }
+}
+
+/// Returns a pseudo-random number below `n`, from a per-thread xorshift
+/// generator.
+fn random_below(n: usize) -> usize {
+    thread_local! {
+        // `RandomState` is seeded differently on every thread; xorshift only
+        // needs the seed to be non-zero.
+        static STATE: Cell<u64> = Cell::new(RandomState::new().build_hasher().finish() | 1);
+    }
+    STATE.with(|state| {
+        let mut x = state.get();
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        state.set(x);
+        (x % n as u64) as usize
+    })
+}
+
+/// A channel end that can take part in a [`Select`].
+trait Selectable {
+    fn is_ready(&self) -> bool;
+    fn register(&self, waker: &Waker) -> usize;
+    fn unregister(&self, id: usize);
+}
+
+impl<T, S: QueueStorage<Item = T>> Selectable for Receiver<T, S> {
+    fn is_ready(&self) -> bool {
+        !self.buffer.is_empty() || self.inner.can_recv(&lock(&self.inner.shared))
+    }
+
+    fn register(&self, waker: &Waker) -> usize {
+        let mut shared = lock(&self.inner.shared);
+        shared.recv_wakers.register(waker)
+    }
+
+    fn unregister(&self, id: usize) {
+        let mut shared = lock(&self.inner.shared);
+        shared.recv_wakers.unregister(id);
+    }
+}
+
+impl<T, S: QueueStorage<Item = T>> Selectable for mpmc::Receiver<T, S> {
+    fn is_ready(&self) -> bool {
+        self.inner.can_recv(&lock(&self.inner.shared))
+    }
+
+    fn register(&self, waker: &Waker) -> usize {
+        let mut shared = lock(&self.inner.shared);
+        shared.recv_wakers.register(waker)
+    }
+
+    fn unregister(&self, id: usize) {
+        let mut shared = lock(&self.inner.shared);
+        shared.recv_wakers.unregister(id);
+    }
+}
+
+impl<T, S: QueueStorage<Item = T>> Selectable for Sender<T, S> {
+    fn is_ready(&self) -> bool {
+        self.inner.can_send(&lock(&self.inner.shared))
+    }
+
+    fn register(&self, waker: &Waker) -> usize {
+        let mut shared = lock(&self.inner.shared);
+        shared.send_wakers.register(waker)
+    }
+
+    fn unregister(&self, id: usize) {
+        let mut shared = lock(&self.inner.shared);
+        shared.send_wakers.unregister(id);
+    }
+}
+
+/// Wakes a thread blocked in [`Select`].
+#[derive(Default)]
+struct Signal {
+    woken: Mutex<bool>,
+    condvar: Condvar,
+}
+
+impl Signal {
+    /// Waits to be woken, returning false if `deadline` passed first.
+    fn wait(&self, deadline: Option<Instant>) -> bool {
+        let mut woken = lock(&self.woken);
+        while !*woken {
+            let timed_out;
+            (woken, timed_out) = wait_until(&self.condvar, woken, deadline);
+            if timed_out {
+                return false;
+            }
+        }
+        *woken = false;
+        true
+    }
+}
+
+impl Wake for Signal {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        *lock(&self.woken) = true;
+        self.condvar.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Select, channel, sync_channel};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn picks_the_ready_receiver() {
+        let (tx1, mut rx1) = channel::<i32>();
+        let (tx2, rx2) = channel::<i32>();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            tx1.send(7).unwrap();
+        });
+        let mut select = Select::new();
+        let first = select.recv(&rx1);
+        select.recv(&rx2);
+        assert_eq!(select.ready(), first);
+        assert_eq!(rx1.try_recv(), Ok(7));
+        drop(tx2);
+    }
+
+    #[test]
+    fn disconnection_is_ready() {
+        let (tx, rx) = channel::<i32>();
+        let mut select = Select::new();
+        let index = select.recv(&rx);
+        assert_eq!(select.ready_timeout(Duration::from_millis(10)), None);
+        drop(tx);
+        assert_eq!(select.ready_timeout(Duration::from_millis(10)), Some(index));
+    }
+
+    #[test]
+    fn waits_for_sender_capacity() {
+        let (tx, mut rx) = sync_channel(1);
+        tx.send(1).unwrap();
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            (rx.recv(), rx.recv())
+        });
+        let mut select = Select::new();
+        let index = select.send(&tx);
+        assert_eq!(select.try_ready(), None);
+        assert_eq!(select.ready(), index);
+        tx.send(2).unwrap();
+        drop(tx);
+        assert_eq!(handle.join().unwrap(), (Some(1), Some(2)));
+    }
+
+    #[test]
+    fn busy_channel_does_not_starve_others() {
+        let (data_tx, mut data) = channel();
+        let (shutdown_tx, mut shutdown) = channel();
+        data_tx.send_all(0..1000).unwrap();
+        shutdown_tx.send(()).unwrap();
+        let mut received = 0;
+        loop {
+            let mut select = Select::new();
+            let on_data = select.recv(&data);
+            let on_shutdown = select.recv(&shutdown);
+            let index = select.ready();
+            if index == on_shutdown {
+                assert_eq!(shutdown.try_recv(), Ok(()));
+                break;
+            }
+            assert_eq!(index, on_data);
+            assert_eq!(data.try_recv(), Ok(received));
+            received += 1;
+        }
+        // Both were ready on every pass, so shutdown won one long before the
+        // data ran out.
+        assert!(received < 1000);
+    }
+}
@@ -0,0 +1,84 @@
+use std::collections::{LinkedList, VecDeque};
+
+/// The queue a channel keeps behind its `Mutex`.
+///
+/// Implement it to back [`channel_with`](crate::channel_with) with your own
+/// structure, e.g. an arena-backed or deduplicating queue. The channel only
+/// ever calls it with the lock held.
+pub trait QueueStorage: Default {
+    type Item;
+
+    /// Adds a value to the queue.
+    fn push(&mut self, value: Self::Item);
+
+    /// Removes the value that should be received next.
+    fn pop(&mut self) -> Option<Self::Item>;
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Creates an empty queue with room for `capacity` values, for
+    /// [`ChannelBuilder::preallocate`](crate::ChannelBuilder::preallocate).
+    /// The default ignores `capacity`.
+    fn with_capacity(capacity: usize) -> Self {
+        let _ = capacity;
+        Self::default()
+    }
+
+    /// Removes the value that has been queued the longest, for
+    /// [`OverflowPolicy::DropOldest`](crate::OverflowPolicy::DropOldest).
+    ///
+    /// The default pops, which is right for FIFO storage only.
+    fn evict_oldest(&mut self) -> Option<Self::Item> {
+        self.pop()
+    }
+
+    /// Moves every queued value into `buffer`, the receiver's already
+    /// drained local batch, so it can be consumed without the lock.
+    ///
+    /// The default swaps the two queues. Storage whose order depends on
+    /// values arriving later should leave `self` untouched instead, which
+    /// makes the receiver pop one value per lock.
+    fn swap_out_all(&mut self, buffer: &mut Self) {
+        std::mem::swap(self, buffer);
+    }
+}
+
+impl<T> QueueStorage for VecDeque<T> {
+    type Item = T;
+
+    fn push(&mut self, value: T) {
+        self.push_back(value);
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        self.pop_front()
+    }
+
+    fn len(&self) -> usize {
+        VecDeque::len(self)
+    }
+
+    fn with_capacity(capacity: usize) -> Self {
+        VecDeque::with_capacity(capacity)
+    }
+}
+
+impl<T> QueueStorage for LinkedList<T> {
+    type Item = T;
+
+    fn push(&mut self, value: T) {
+        self.push_back(value);
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        self.pop_front()
+    }
+
+    fn len(&self) -> usize {
+        LinkedList::len(self)
+    }
+}
@@ -0,0 +1,46 @@
+use std::task::Waker;
+
+/// Wakers registered to hear about a change in a channel's state, kept in
+/// `Shared` under the channel lock.
+///
+/// Entries stay registered until their owner removes them, so a waker may
+/// be woken any number of times.
+#[derive(Default)]
+pub(crate) struct Wakers {
+    next_id: usize,
+    entries: Vec<(usize, Waker)>,
+}
+
+impl Wakers {
+    /// Registers a waker, returning the id to unregister it with.
+    pub(crate) fn register(&mut self, waker: &Waker) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.entries.push((id, waker.clone()));
+        id
+    }
+
+    /// Registers a waker under `id`, or replaces the one already registered
+    /// there, e.g. when a future is polled again from another task.
+    pub(crate) fn register_or_update(&mut self, id: &mut Option<usize>, waker: &Waker) {
+        let Some(id) = *id else {
+            *id = Some(self.register(waker));
+            return;
+        };
+        if let Some((_, registered)) = self.entries.iter_mut().find(|(entry, _)| *entry == id)
+            && !registered.will_wake(waker)
+        {
+            *registered = waker.clone();
+        }
+    }
+
+    pub(crate) fn unregister(&mut self, id: usize) {
+        self.entries.retain(|(entry, _)| *entry != id);
+    }
+
+    pub(crate) fn wake_all(&self) {
+        for (_, waker) in &self.entries {
+            waker.wake_by_ref();
+        }
+    }
+}
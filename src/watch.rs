@@ -0,0 +1,229 @@
+//! A channel holding only the latest value, for propagating state.
+//!
+//! The sender overwrites a single slot and bumps its version; receivers read
+//! the current value with [`Receiver::borrow`] and wait for a newer version
+//! with [`Receiver::changed`].
+
+use crate::{RecvError, SendError, lock, wait_until};
+use std::ops::Deref;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+
+pub struct Sender<T> {
+    inner: Arc<Inner<T>>,
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut shared = lock(&self.inner.shared);
+        shared.sender_dropped = true;
+        drop(shared);
+        self.inner.changed.notify_all()
+    }
+}
+
+impl<T> Sender<T> {
+    /// Publishes a new value, handing it back if every receiver is gone.
+    pub fn send(&self, value: T) -> Result<(), SendError<T>> {
+        let mut shared = lock(&self.inner.shared);
+        if shared.receivers == 0 {
+            return Err(SendError(value));
+        }
+        let old = std::mem::replace(&mut shared.value, value);
+        shared.version += 1;
+        drop(shared);
+        self.inner.changed.notify_all();
+        // Dropped unlocked, like everything the channels evict.
+        drop(old);
+        Ok(())
+    }
+
+    /// Returns the current value. The channel is locked while the returned
+    /// guard lives, so keep it short.
+    pub fn borrow(&self) -> Ref<'_, T> {
+        Ref {
+            shared: lock(&self.inner.shared),
+        }
+    }
+
+    /// Creates a receiver that has seen the current value.
+    pub fn subscribe(&self) -> Receiver<T> {
+        let mut shared = lock(&self.inner.shared);
+        shared.receivers += 1;
+        let seen = shared.version;
+        drop(shared);
+        Receiver {
+            inner: Arc::clone(&self.inner),
+            seen,
+        }
+    }
+}
+
+pub struct Receiver<T> {
+    inner: Arc<Inner<T>>,
+    /// Version of the last value this receiver was told about.
+    seen: u64,
+}
+
+impl<T> Clone for Receiver<T> {
+    fn clone(&self) -> Self {
+        let mut shared = lock(&self.inner.shared);
+        shared.receivers += 1;
+        drop(shared);
+        Self {
+            inner: Arc::clone(&self.inner),
+            seen: self.seen,
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let mut shared = lock(&self.inner.shared);
+        shared.receivers -= 1;
+    }
+}
+
+impl<T> Receiver<T> {
+    /// Returns the current value without marking it as seen. The channel is
+    /// locked while the returned guard lives, so keep it short.
+    pub fn borrow(&self) -> Ref<'_, T> {
+        Ref {
+            shared: lock(&self.inner.shared),
+        }
+    }
+
+    /// Returns the current value and marks it as seen.
+    pub fn borrow_and_update(&mut self) -> Ref<'_, T> {
+        let shared = lock(&self.inner.shared);
+        self.seen = shared.version;
+        Ref { shared }
+    }
+
+    /// Whether a value newer than the last one seen has been published.
+    pub fn has_changed(&self) -> bool {
+        lock(&self.inner.shared).version != self.seen
+    }
+
+    /// Blocks until a value newer than the last one seen is published, and
+    /// marks it as seen. Fails once the sender is gone without publishing
+    /// anything newer.
+    pub fn changed(&mut self) -> Result<(), RecvError> {
+        let mut shared = lock(&self.inner.shared);
+        loop {
+            if shared.version != self.seen {
+                self.seen = shared.version;
+                return Ok(());
+            }
+            if shared.sender_dropped {
+                return Err(RecvError);
+            }
+            shared = wait_until(&self.inner.changed, shared, None).0;
+        }
+    }
+}
+
+/// A borrowed watch value, holding the channel lock.
+pub struct Ref<'a, T> {
+    shared: MutexGuard<'a, Shared<T>>,
+}
+
+impl<T> Deref for Ref<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.shared.value
+    }
+}
+
+struct Inner<T> {
+    shared: Mutex<Shared<T>>,
+    changed: Condvar,
+}
+
+struct Shared<T> {
+    value: T,
+    version: u64,
+    sender_dropped: bool,
+    receivers: usize,
+}
+
+/// Creates a watch channel starting out with `initial`, which receivers
+/// count as already seen.
+pub fn channel<T>(initial: T) -> (Sender<T>, Receiver<T>) {
+    let inner = Arc::new(Inner {
+        shared: Mutex::new(Shared {
+            value: initial,
+            version: 0,
+            sender_dropped: false,
+            receivers: 1,
+        }),
+        changed: Condvar::new(),
+    });
+    (
+        Sender {
+            inner: inner.clone(),
+        },
+        Receiver { inner, seen: 0 },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::channel;
+    use crate::{RecvError, SendError};
+    use std::thread;
+
+    #[test]
+    fn receivers_see_latest_value() {
+        let (tx, mut rx) = channel("initial");
+        assert_eq!(*rx.borrow(), "initial");
+        assert!(!rx.has_changed());
+        tx.send("first").unwrap();
+        tx.send("second").unwrap();
+        assert!(rx.has_changed());
+        rx.changed().unwrap();
+        assert_eq!(*rx.borrow(), "second");
+        assert!(!rx.has_changed());
+    }
+
+    #[test]
+    fn changed_waits_for_new_version() {
+        let (tx, mut rx) = channel(0);
+        let handle = thread::spawn(move || {
+            let mut seen = Vec::new();
+            while rx.changed().is_ok() {
+                seen.push(*rx.borrow());
+            }
+            seen
+        });
+        tx.send(1).unwrap();
+        drop(tx);
+        let seen = handle.join().unwrap();
+        assert_eq!(seen, [1]);
+    }
+
+    #[test]
+    fn closed_ends() {
+        let (tx, mut rx) = channel(0);
+        drop(tx);
+        assert_eq!(rx.changed(), Err(RecvError));
+        let (tx, rx) = channel(0);
+        drop(rx);
+        assert_eq!(tx.send(1), Err(SendError(1)));
+    }
+
+    #[test]
+    fn survives_panic_while_borrowed() {
+        let (tx, mut rx) = channel(0);
+        let borrower = rx.clone();
+        let result = thread::spawn(move || {
+            let _value = borrower.borrow();
+            panic!("holding the value");
+        })
+        .join();
+        assert!(result.is_err());
+        tx.send(1).unwrap();
+        rx.changed().unwrap();
+        assert_eq!(*rx.borrow(), 1);
+    }
+}